@@ -17,22 +17,22 @@ pub trait PutTriad {
 impl<T: Buf + ?Sized> GetTriad for T {
     fn get_u24(&mut self) -> u32 {
         let mut bytes = self.take(3);
-        (bytes.get_u8() as u32) << 16 & bytes.get_u16() as u32
+        (bytes.get_u8() as u32) << 16 | bytes.get_u16() as u32
     }
 
     fn get_u24_le(&mut self) -> u32 {
         let mut bytes = self.take(3);
-        bytes.get_u8() as u32 & ((bytes.get_u16_le() as u32) << 8)
+        bytes.get_u8() as u32 | (bytes.get_u16_le() as u32) << 8
     }
 
     fn get_i24(&mut self) -> i32 {
         let unsigned = self.get_u24();
-        (unsigned & !0x7fffff << 8 & unsigned & 0x7fffff) as i32
+        ((unsigned << 8) as i32) >> 8
     }
 
     fn get_i24_le(&mut self) -> i32 {
         let unsigned = self.get_u24_le();
-        (unsigned & !0x7fffff << 8 & unsigned & 0x7fffff) as i32
+        ((unsigned << 8) as i32) >> 8
     }
 }
 
@@ -50,10 +50,10 @@ impl <T: BufMut + ?Sized> PutTriad for T {
     }
 
     fn put_i24_be(&mut self, n: i32) {
-        self.put_u24_be((n & 0x7fffff & (n >> 8)) as u32);
+        self.put_u24_be(n as u32 & 0xffffff);
     }
 
     fn put_i24_le(&mut self, n: i32) {
-        self.put_u24_le((n & 0x7fffff & (n >> 8)) as u32);
+        self.put_u24_le(n as u32 & 0xffffff);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,760 @@
+use std::net::{UdpSocket, SocketAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+use std::ops::Deref;
+use std::convert::TryFrom;
+use std::thread::sleep;
+use parking_lot::{Mutex, MutexGuard};
+use log::debug;
+use crate::client::{ClientEventListener, ConnectError, MtuDiscovery};
+use crate::generic::{ReceiveReliabilityLayer, SendReliabilityLayer, RttEstimator, DatagramCipher, datagram_mtu_size};
+#[cfg(feature = "security")]
+use crate::generic::{KeyPair, SessionCipher};
+use crate::protocol::{OpenConnectionRequest2, OpenConnectionReply2, IncompatibleProtocolVersion, RemoteSystemRequiresPubKey, NoFreeIncomingConnections, ConnectionBanned, IpRecentlyConnected, ConnectionRequest, ConnectionRequestAccepted, NewIncomingConnection, ConnectedPing, ConnectedPong, DisconnectionNotification, Datagram, ACK, NACK, EncapsulatedPacket, PacketReliability, MessageIdentifiers, MessageIdentifierHeader, DecodePacket, PacketImpl, DecodeError};
+use crate::server::{SessionState, ServerConfig};
+use crate::RaknetTime;
+
+pub struct Client<'a> {
+	internal: Mutex<ClientInternal<'a>>,
+	export: Arc<ClientExport<'a>>
+}
+
+impl<'a> Deref for Client<'a> {
+	type Target = ClientExport<'a>;
+
+	fn deref(&self) -> &Self::Target {
+		self.export.deref()
+	}
+}
+
+impl<'a> Client<'a> {
+
+	const RAKLIB_TIME_PER_TICK: Duration = Duration::from_millis(10);
+
+	const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+	const HANDSHAKE_ATTEMPTS: usize = 4;
+
+	/// Smallest MTU size the server may choose, the same minimum a `ServerConfig` accepts
+	pub const MIN_MTU_SIZE: usize = ServerConfig::MIN_SUPPORTED_MTU_SIZE;
+
+	pub fn connect(
+		server_address: SocketAddr,
+		client_id: u64,
+		protocol_version: u8,
+		event_listener: impl ClientEventListener + 'a
+	) -> Result<Self, ConnectError> {
+		Self::connect_with_mtu_discovery(server_address, client_id, protocol_version, &MtuDiscovery::default(), event_listener)
+	}
+
+	/**
+	 * Performs the offline handshake with the server and queues the connection request.
+	 * The connection is established once `run` receives the server's acceptance.
+	 * With the `security` feature, the connection is encrypted if the server has security.
+	 */
+	pub fn connect_with_mtu_discovery(
+		server_address: SocketAddr,
+		client_id: u64,
+		protocol_version: u8,
+		mtu_discovery: &MtuDiscovery,
+		event_listener: impl ClientEventListener + 'a
+	) -> Result<Self, ConnectError> {
+		Self::open(
+			server_address,
+			client_id,
+			protocol_version,
+			mtu_discovery,
+			#[cfg(feature = "security")]
+			None,
+			event_listener
+		)
+	}
+
+	/**
+	 * Connects only if the server has security and owns the key pair of the given public key
+	 */
+	#[cfg(feature = "security")]
+	pub fn connect_secure(
+		server_address: SocketAddr,
+		client_id: u64,
+		protocol_version: u8,
+		server_public_key: [u8; 32],
+		event_listener: impl ClientEventListener + 'a
+	) -> Result<Self, ConnectError> {
+		Self::open(server_address, client_id, protocol_version, &MtuDiscovery::default(), Some(server_public_key), event_listener)
+	}
+
+	fn open(
+		server_address: SocketAddr,
+		client_id: u64,
+		protocol_version: u8,
+		mtu_discovery: &MtuDiscovery,
+		#[cfg(feature = "security")]
+		server_public_key: Option<[u8; 32]>,
+		event_listener: impl ClientEventListener + 'a
+	) -> Result<Self, ConnectError> {
+		let bind_address = match server_address {
+			SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+			SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+		};
+		let udp_socket = UdpSocket::bind(bind_address)?;
+		udp_socket.connect(server_address)?;
+
+		let reply1 = mtu_discovery.discover(&udp_socket, protocol_version)?;
+		debug!("Discovered MTU size {} to {}", reply1.mtu_size, server_address);
+
+		#[cfg(feature = "security")]
+		let server_public_key = match (server_public_key, reply1.public_key) {
+			(Some(pinned), Some(public_key)) if pinned != public_key => return Err(ConnectError::PubKeyMismatch),
+			(Some(_), None) => return Err(ConnectError::OurSystemRequiresSecurity),
+			(_, public_key) => public_key
+		};
+		#[cfg(feature = "security")]
+		let ephemeral_key_pair = KeyPair::generate();
+		#[cfg(feature = "security")]
+		let challenge = server_public_key.map(| _ | ephemeral_key_pair.get_public_key());
+		#[cfg(not(feature = "security"))]
+		let challenge = None;
+
+		let reply2: OpenConnectionReply2 = handshake(&udp_socket, &OpenConnectionRequest2 {
+			offline_message: Default::default(),
+			cookie: reply1.cookie,
+			challenge,
+			client_id,
+			server_address,
+			mtu_size: reply1.mtu_size
+		}, Self::HANDSHAKE_ATTEMPTS, Self::HANDSHAKE_TIMEOUT)?;
+		if (reply2.mtu_size as usize) < Self::MIN_MTU_SIZE || reply2.mtu_size > reply1.mtu_size {
+			return Err(ConnectError::InvalidMtuSize {
+				mtu_size: reply2.mtu_size
+			});
+		}
+		debug!("Opened connection to {} with MTU size {}", server_address, reply2.mtu_size);
+
+		#[cfg(feature = "security")]
+		let cipher = match (server_public_key, reply2.answer) {
+			(Some(server_public_key), Some(answer)) => match SessionCipher::client(&ephemeral_key_pair, &server_public_key, &answer) {
+				Some(cipher) => Some(Arc::new(cipher) as Arc<dyn DatagramCipher>),
+				None => return Err(ConnectError::PubKeyMismatch)
+			},
+			(Some(_), None) => return Err(ConnectError::OurSystemRequiresSecurity),
+			_ => None
+		};
+		#[cfg(not(feature = "security"))]
+		let cipher = None;
+
+		let export = Arc::new(ClientExport::new(
+			client_id,
+			reply1.server_id,
+			server_address,
+			reply1.mtu_size as usize,
+			reply2.mtu_size as usize,
+			ClientSocket::new(udp_socket, cipher),
+			event_listener
+		));
+		let client = Self {
+			internal: Mutex::new(ClientInternal::new(export.clone())),
+			export
+		};
+		client.send_layer.lock().queue_connected_packet(&ConnectionRequest {
+			client_id,
+			send_ping_time: client.get_raknet_time(),
+			use_security: client.socket.cipher.is_some()
+		}, PacketReliability::Reliable, 0, true);
+		Ok(client)
+	}
+
+	/**
+	 * Processes incoming packets and ticks the connection until it is fully disconnected.
+	 */
+	pub fn run(&self) {
+		let mut buffer = vec![0; self.mtu_size];
+		while !self.is_fully_disconnected() {
+			let deadline = Instant::now() + Self::RAKLIB_TIME_PER_TICK;
+			loop {
+				match receive_until(&self.socket.udp_socket, &mut buffer, deadline) {
+					Ok(Some(read)) => self.internal.lock().receive_packet(&buffer[..read]),
+					Ok(None) => break,
+					Err(e) => {
+						debug!("{:?}", e);
+						sleep(deadline.saturating_duration_since(Instant::now()));
+						break;
+					}
+				}
+			}
+			self.internal.lock().update(Instant::now());
+		}
+	}
+
+	pub fn get_mut(&self) -> MutexGuard<'_, ClientInternal<'a>> {
+		self.internal.lock()
+	}
+
+	/**
+	 * Limits the parts of split packets in both directions, it has to match the server's `max_split_part_count`
+	 */
+	pub fn set_max_split_part_count(&self, max_split_part_count: usize) {
+		self.internal.lock().recv_layer.set_max_split_part_count(max_split_part_count);
+		self.send_layer.lock().set_max_split_part_count(max_split_part_count);
+	}
+}
+
+/**
+ * Sends the request until the expected reply arrives, giving up after the given number of attempts.
+ */
+pub(super) fn handshake<T: DecodePacket + MessageIdentifierHeader>(
+	udp_socket: &UdpSocket,
+	request: &impl PacketImpl,
+	attempts: usize,
+	timeout: Duration
+) -> Result<T, ConnectError> {
+	let mut send_buffer = Vec::new();
+	request.encode_packet(&mut send_buffer);
+	let mut buffer = vec![0; 2048];
+
+	for _ in 0..attempts {
+		udp_socket.send(&send_buffer)?;
+		let deadline = Instant::now() + timeout;
+		while let Some(read) = receive_until(udp_socket, &mut buffer, deadline)? {
+			let mut raw = &buffer[..read];
+			match raw.first() {
+				Some(id) if *id == T::ID as u8 => match T::decode_packet(&mut raw) {
+					Ok(reply) => return Ok(reply),
+					Err(e) => debug!("Dropped bad handshake reply: {}", e)
+				},
+				Some(id) if *id == RemoteSystemRequiresPubKey::ID as u8 && RemoteSystemRequiresPubKey::decode_packet(&mut raw).is_ok() => {
+					return Err(ConnectError::RemoteSystemRequiresPubKey);
+				},
+				Some(id) if *id == NoFreeIncomingConnections::ID as u8 && NoFreeIncomingConnections::decode_packet(&mut raw).is_ok() => {
+					return Err(ConnectError::NoFreeIncomingConnections);
+				},
+				Some(id) if *id == ConnectionBanned::ID as u8 && ConnectionBanned::decode_packet(&mut raw).is_ok() => {
+					return Err(ConnectError::ConnectionBanned);
+				},
+				Some(id) if *id == IpRecentlyConnected::ID as u8 && IpRecentlyConnected::decode_packet(&mut raw).is_ok() => {
+					return Err(ConnectError::IpRecentlyConnected);
+				},
+				Some(id) if *id == IncompatibleProtocolVersion::ID as u8 => {
+					if let Ok(packet) = IncompatibleProtocolVersion::decode_packet(&mut raw) {
+						return Err(ConnectError::IncompatibleProtocolVersion {
+							server_version: packet.protocol_version
+						});
+					}
+				},
+				_ => {}
+			}
+		}
+	}
+	Err(ConnectError::Timeout)
+}
+
+/**
+ * Blocks until a packet arrives or the deadline passes.
+ */
+fn receive_until(udp_socket: &UdpSocket, buffer: &mut [u8], deadline: Instant) -> io::Result<Option<usize>> {
+	let now = Instant::now();
+	if now >= deadline {
+		return Ok(None);
+	}
+	udp_socket.set_read_timeout(Some(deadline - now))?;
+	match udp_socket.recv(buffer) {
+		Ok(read) => Ok(Some(read)),
+		Err(e) => match e.kind() {
+			ErrorKind::WouldBlock | ErrorKind::TimedOut => Ok(None),
+			_ => Err(e)
+		}
+	}
+}
+
+pub struct ClientInternal<'a> {
+
+	export: Arc<ClientExport<'a>>,
+
+	last_update: Instant,
+
+	is_active: bool, //default false
+
+	last_ping_time: Instant,
+
+	recv_layer: ReceiveReliabilityLayer<'a>
+}
+
+impl<'a> Deref for ClientInternal<'a> {
+	type Target = ClientExport<'a>;
+
+	fn deref(&self) -> &Self::Target {
+		self.export.deref()
+	}
+}
+
+impl<'a> ClientInternal<'a> {
+
+	pub const MAX_SPLIT_PART_COUNT: usize = SendReliabilityLayer::DEFAULT_MAX_SPLIT_PART_COUNT;
+	pub const MAX_CONCURRENT_SPLIT_COUNT: usize = 4;
+	pub const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+	pub const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+	fn new(export: Arc<ClientExport<'a>>) -> Self {
+		let export_route = export.clone();
+		let export_clone = export.clone();
+		let mut recv_layer = ReceiveReliabilityLayer::with_split_limit(
+			move | pk | {
+				export_route.handle_encapsulated_packet_route(pk);
+			},
+			move | pk | {
+				export_clone.send_packet(&pk);
+			},
+			Self::MAX_SPLIT_PART_COUNT,
+			Self::MAX_CONCURRENT_SPLIT_COUNT
+		);
+		//ACKs and NACKs are encrypted as well
+		recv_layer.set_mtu_size(datagram_mtu_size(export.mtu_size, export.socket.cipher.as_deref()));
+		Self {
+			export,
+			last_update: Instant::now(),
+			is_active: false,
+			last_ping_time: Instant::now(),
+			recv_layer
+		}
+	}
+
+	fn receive_packet(&mut self, buffer: &[u8]) {
+		let decrypted;
+		let mut buffer = buffer;
+		if let Some(cipher) = &self.socket.cipher {
+			match cipher.decrypt(buffer) {
+				Some(payload) => {
+					decrypted = payload;
+					buffer = &decrypted;
+				},
+				None => {
+					debug!("Dropped packet from {} which failed to decrypt", self.server_address);
+					return;
+				}
+			}
+		}
+		let header = match buffer.first() {
+			Some(header) => *header,
+			None => return
+		};
+		if (header & Datagram::FLAG_VALID) != 0 {
+			let result = if (header & Datagram::FLAG_ACK) != 0 {
+				ACK::decode_packet(&mut buffer).map(| packet | self.handle_ack(packet))
+			} else if (header & Datagram::FLAG_NAK) != 0 {
+				NACK::decode_packet(&mut buffer).map(| packet | self.handle_nack(packet))
+			} else {
+				Datagram::decode_packet(&mut buffer).map(| packet | self.handle_datagram(packet))
+			};
+			if let Err(e) = result {
+				debug!("Dropped bad packet from {}: {}", self.server_address, e);
+			}
+		} else {
+			debug!("Ignored unconnected packet from {} ({:#04x})", self.server_address, header);
+		}
+	}
+
+	pub fn update(&mut self, time: Instant) {
+		let timeout = Self::SESSION_TIMEOUT;
+		if !self.is_active && time.duration_since(self.last_update) > timeout {
+			self.forcibly_disconnect("timeout");
+
+			return;
+		}
+
+		let mut send_layer = self.export.send_layer.lock();
+
+		let mut state = self.export.state.lock();
+
+		if let SessionState::Disconnecting { disconnection_time } = *state {
+			if !self.recv_layer.needs_update() && !send_layer.needs_update() {
+				*state = SessionState::Disconnected {
+					disconnection_time
+				};
+				debug!("Cleanly disconnected from {}", self.server_address);
+				return;
+			} else if time.duration_since(disconnection_time) > timeout {
+				*state = SessionState::Disconnected {
+					disconnection_time
+				};
+				debug!("Timeout during graceful disconnect, forcibly closing connection");
+				return;
+			}
+		}
+		drop(state);
+
+		self.is_active = false;
+
+		let is_sending = send_layer.update();
+		self.recv_layer.update(is_sending);
+		drop(send_layer);
+
+		if time.duration_since(self.last_ping_time) > Self::PING_INTERVAL {
+			self.send_ping();
+			self.last_ping_time = time;
+		}
+	}
+
+	fn handle_datagram(&mut self, mut datagram: Datagram) {
+		self.is_active = true;
+		self.last_update = Instant::now();
+		self.recv_layer.on_datagram(&mut datagram);
+	}
+
+	fn handle_ack(&mut self, ack: ACK) {
+		self.is_active = true;
+		self.last_update = Instant::now();
+		self.send_layer.lock().on_ack(&ack);
+	}
+
+	fn handle_nack(&mut self, nack: NACK) {
+		self.is_active = true;
+		self.last_update = Instant::now();
+		self.send_layer.lock().on_nack(&nack);
+	}
+}
+
+struct ClientSocket {
+	udp_socket: UdpSocket,
+	send_buffer: Mutex<Vec<u8>>,
+	cipher: Option<Arc<dyn DatagramCipher>> //set if the connection negotiated security
+}
+
+impl ClientSocket {
+	fn new(udp_socket: UdpSocket, cipher: Option<Arc<dyn DatagramCipher>>) -> Self {
+		Self {
+			udp_socket,
+			send_buffer: Mutex::new(vec![]),
+			cipher
+		}
+	}
+
+	fn send_packet(&self, packet: &impl PacketImpl) {
+		let mut buffer = self.send_buffer.lock();
+		buffer.clear();
+		packet.encode_packet(&mut *buffer);
+		let result = match &self.cipher {
+			Some(cipher) => self.udp_socket.send(&cipher.encrypt(&buffer)),
+			None => self.udp_socket.send(&buffer)
+		};
+		if let Err(e) = result {
+			debug!("{}", e);
+		}
+	}
+}
+
+pub struct ClientExport<'a> {
+
+	pub id: u64,
+
+	pub server_id: u64,
+
+	pub server_address: SocketAddr,
+
+	pub discovered_mtu_size: usize,
+
+	pub mtu_size: usize,
+
+	pub start_time: Instant,
+
+	socket: Arc<ClientSocket>,
+
+	state: Mutex<SessionState>, //default SessionState::Connecting
+
+	last_ping_measure: Mutex<Duration>,
+
+	send_layer: Mutex<SendReliabilityLayer<'a>>,
+
+	event_listener: Arc<Mutex<Box<dyn ClientEventListener + 'a>>>
+}
+
+impl<'a> ClientExport<'a> {
+	fn new(
+		id: u64,
+		server_id: u64,
+		server_address: SocketAddr,
+		discovered_mtu_size: usize,
+		mtu_size: usize,
+		socket: ClientSocket,
+		event_listener: impl ClientEventListener + 'a
+	) -> Self {
+		let datagram_mtu_size = datagram_mtu_size(mtu_size, socket.cipher.as_deref());
+		let socket = Arc::new(socket);
+		let event_listener: Arc<Mutex<Box<dyn ClientEventListener + 'a>>> = Arc::new(Mutex::new(Box::new(event_listener)));
+		let socket_clone = socket.clone();
+		let event_listener_clone = event_listener.clone();
+		let event_listener_loss = event_listener.clone();
+		Self {
+			id,
+			server_id,
+			server_address,
+			discovered_mtu_size,
+			mtu_size,
+			start_time: Instant::now(),
+			socket,
+			state: Mutex::new(SessionState::Connecting),
+			last_ping_measure: Mutex::new(Default::default()),
+			send_layer: Mutex::new(SendReliabilityLayer::new(
+				datagram_mtu_size,
+				move | datagram | {
+					socket_clone.send_packet(datagram)
+				},
+				move | identifier_ack | {
+					event_listener_clone.lock().on_packet_ack(identifier_ack)
+				},
+				move | identifier_ack | {
+					event_listener_loss.lock().on_packet_loss(identifier_ack)
+				}
+			)),
+			event_listener
+		}
+	}
+
+	pub fn get_raknet_time(&self) -> RaknetTime {
+		self.start_time.elapsed()
+	}
+
+	pub fn get_last_ping_measure(&self) -> Duration {
+		*self.last_ping_measure.lock()
+	}
+
+	/**
+	 * Round trip time statistics from acknowledgements and pings
+	 */
+	pub fn get_rtt(&self) -> RttEstimator {
+		*self.send_layer.lock().get_rtt()
+	}
+
+	/**
+	 * Queues a user packet. The first byte of the payload is the packet id and must not be below
+	 * `MessageIdentifiers::UserPacketEnum`.
+	 */
+	pub fn send(&self, payload: &[u8], reliability: PacketReliability, order_channel: u8, immediate: bool) {
+		let mut encapsulated = EncapsulatedPacket::default();
+		encapsulated.reliability = reliability;
+		encapsulated.order_channel = Some(order_channel);
+		encapsulated.buffer = payload.to_vec();
+		self.send_encapsulated(encapsulated, immediate);
+	}
+
+	pub fn send_encapsulated(&self, packet: EncapsulatedPacket, immediate: bool) {
+		self.send_layer.lock().add_encapsulated_to_queue(packet, immediate);
+	}
+
+	pub fn send_packet(&self, packet: &impl PacketImpl) {
+		self.socket.send_packet(packet);
+	}
+
+	fn send_ping(&self) {
+		self.send_layer.lock().queue_connected_packet(&ConnectedPing {
+			send_ping_time: self.get_raknet_time()
+		}, PacketReliability::Unreliable, 0, true);
+	}
+
+	fn handle_encapsulated_packet_route(&self, packet: &mut EncapsulatedPacket) {
+		let id = match packet.buffer.first() {
+			Some(id) => *id,
+			None => return
+		};
+		let state = *self.state.lock();
+		if id < MessageIdentifiers::UserPacketEnum as u8 { //internal data packet
+			if let Err(e) = self.handle_internal_packet(id, state, &packet.buffer) {
+				debug!("Dropped bad packet from {}: {}", self.server_address, e);
+			}
+		} else if state == SessionState::Connected {
+			self.event_listener.lock().on_packet_receive(&packet.buffer)
+		}
+	}
+
+	fn handle_internal_packet(&self, id: u8, state: SessionState, mut buffer: &[u8]) -> Result<(), DecodeError> {
+		let id = match MessageIdentifiers::try_from(id) {
+			Ok(id) => id,
+			Err(_) => return Ok(())
+		};
+		if state == SessionState::Connecting {
+			if let ConnectionRequestAccepted::ID = id {
+				let data_packet = ConnectionRequestAccepted::decode_packet(&mut buffer)?;
+				self.send_layer.lock().queue_connected_packet(&NewIncomingConnection {
+					address: self.server_address,
+					system_addresses: vec![],
+					send_ping_time: data_packet.send_pong_time,
+					send_pong_time: self.get_raknet_time()
+				}, PacketReliability::ReliableOrdered, 0, true);
+				*self.state.lock() = SessionState::Connected;
+				self.event_listener.lock().on_connect(self.server_address, self.server_id);
+				self.send_ping();
+			}
+		} else {
+			match id {
+				DisconnectionNotification::ID => {
+					self.initiate_disconnect("server disconnect");
+				},
+				ConnectedPing::ID => {
+					let data_packet = ConnectedPing::decode_packet(&mut buffer)?;
+					self.send_layer.lock().queue_connected_packet(&ConnectedPong {
+						send_ping_time: data_packet.send_ping_time,
+						send_pong_time: self.get_raknet_time()
+					}, PacketReliability::Unreliable, 0, false);
+				},
+				ConnectedPong::ID => {
+					let data_packet = ConnectedPong::decode_packet(&mut buffer)?;
+					self.handle_pong(data_packet.send_ping_time);
+				},
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+
+	fn handle_pong(&self, send_ping_time: RaknetTime) {
+		let mut last_ping_measure = self.last_ping_measure.lock();
+		*last_ping_measure = self.get_raknet_time().saturating_sub(send_ping_time);
+		self.send_layer.lock().add_rtt_sample(*last_ping_measure);
+		self.event_listener.lock().on_ping_measure(*last_ping_measure);
+	}
+
+	pub fn is_connected(&self) -> bool {
+		matches!(*self.state.lock(), SessionState::Connected | SessionState::Connecting)
+	}
+
+	/**
+	 * Initiates a graceful disconnect, `run` returns once both parties got all packets.
+	 */
+	pub fn disconnect(&self) {
+		self.initiate_disconnect("client disconnect");
+	}
+
+	fn initiate_disconnect(&self, reason: &str) {
+		if self.is_connected() {
+			*self.state.lock() = SessionState::Disconnecting {
+				disconnection_time: Instant::now()
+			};
+			self.send_layer.lock().queue_connected_packet(&DisconnectionNotification::default(), PacketReliability::ReliableOrdered, 0, true);
+			self.event_listener.lock().on_disconnect(reason);
+			debug!("Requesting graceful disconnect because \"{}\"", reason)
+		}
+	}
+
+	/**
+	 * Disconnects with immediate effect, regardless of current state. Usually used in timeout cases.
+	 */
+	pub fn forcibly_disconnect(&self, reason: &str) {
+		*self.state.lock() = SessionState::Disconnected {
+			disconnection_time: Instant::now()
+		};
+		self.event_listener.lock().on_disconnect(reason);
+		debug!("Forcibly disconnecting due to \"{}\"", reason);
+	}
+
+	pub fn is_fully_disconnected(&self) -> bool {
+		matches!(*self.state.lock(), SessionState::Disconnected { .. })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::{UdpSocket, SocketAddr};
+	use std::thread::{spawn, JoinHandle};
+	use std::time::Duration;
+	use crate::client::{Client, ClientEventListener, ConnectError, MtuDiscovery};
+	use crate::protocol::{OpenConnectionRequest1, OpenConnectionReply1, OpenConnectionRequest2, OpenConnectionReply2, IncompatibleProtocolVersion, DecodePacket, EncodePacket};
+
+	struct EL;
+
+	impl ClientEventListener for EL {
+		fn on_connect(&mut self, _server_address: SocketAddr, _server_id: u64) {}
+
+		fn on_disconnect(&mut self, _reason: &str) {}
+
+		fn on_packet_receive(&mut self, _packet: &[u8]) {}
+
+		fn on_packet_ack(&mut self, _identifier_ack: u64) {}
+
+		fn on_packet_loss(&mut self, _identifier_ack: u64) {}
+
+		fn on_ping_measure(&mut self, _latency: Duration) {}
+	}
+
+	fn reply(socket: &UdpSocket, packet: &impl EncodePacket, address: &SocketAddr) {
+		let mut buffer = Vec::new();
+		packet.encode_packet(&mut buffer);
+		socket.send_to(&buffer, address).unwrap();
+	}
+
+	/// Answers the offline handshake, dropping probes that don't fit in `path_mtu_size`
+	fn fake_server(accept_protocol: bool, path_mtu_size: u16, mtu_size: u16) -> (SocketAddr, JoinHandle<()>) {
+		let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let address = socket.local_addr().unwrap();
+		let handle = spawn(move || {
+			let mut buffer = [0; 2048];
+			let (request1, client) = loop {
+				let (read, client) = socket.recv_from(&mut buffer).unwrap();
+				let request1 = OpenConnectionRequest1::decode_packet(&mut &buffer[..read]).unwrap();
+				assert_eq!(request1.mtu_size as usize, read);
+				if request1.mtu_size + 28 <= path_mtu_size {
+					break (request1, client);
+				}
+			};
+			if !accept_protocol {
+				reply(&socket, &IncompatibleProtocolVersion::create(request1.protocol + 1, 42), &client);
+				return;
+			}
+			reply(&socket, &OpenConnectionReply1::create(42, None, None, request1.mtu_size + 28), &client);
+
+			let (read, client) = socket.recv_from(&mut buffer).unwrap();
+			let request2 = OpenConnectionRequest2::decode_packet(&mut &buffer[..read]).unwrap();
+			assert_eq!(request2.client_id, 7);
+			assert_eq!(request2.mtu_size, request1.mtu_size + 28);
+			reply(&socket, &OpenConnectionReply2::create(42, client, mtu_size, None), &client);
+		});
+		(address, handle)
+	}
+
+	#[test]
+	fn offline_handshake() {
+		let (address, handle) = fake_server(true, 1492, 1400);
+		let client = Client::connect(address, 7, crate::DEFAULT_PROTOCOL_VERSION, EL).unwrap();
+		handle.join().unwrap();
+
+		assert_eq!(client.server_id, 42);
+		assert_eq!(client.discovered_mtu_size, 1492);
+		assert_eq!(client.mtu_size, 1400);
+		assert!(client.is_connected());
+	}
+
+	#[test]
+	fn incompatible_protocol() {
+		let (address, handle) = fake_server(false, 1492, 1400);
+		let result = Client::connect(address, 7, crate::DEFAULT_PROTOCOL_VERSION, EL);
+		handle.join().unwrap();
+
+		assert!(matches!(
+			result,
+			Err(ConnectError::IncompatibleProtocolVersion { server_version }) if server_version == crate::DEFAULT_PROTOCOL_VERSION + 1
+		));
+	}
+
+	#[test]
+	fn mtu_discovery() {
+		let (address, handle) = fake_server(true, 1300, 1200);
+		let mut mtu_discovery = MtuDiscovery::new(vec![576, 1492, 1200, 1400]);
+		mtu_discovery.attempts = 1;
+		mtu_discovery.timeout = Duration::from_millis(50);
+		assert_eq!(mtu_discovery.get_sizes(), &[1492, 1400, 1200, 576]);
+
+		let client = Client::connect_with_mtu_discovery(address, 7, crate::DEFAULT_PROTOCOL_VERSION, &mtu_discovery, EL).unwrap();
+		handle.join().unwrap();
+
+		assert_eq!(client.discovered_mtu_size, 1200);
+	}
+
+	#[test]
+	fn invalid_mtu_size() {
+		for mtu_size in [0, Client::MIN_MTU_SIZE as u16 - 1, 1493] {
+			let (address, handle) = fake_server(true, 1492, mtu_size);
+			let result = Client::connect(address, 7, crate::DEFAULT_PROTOCOL_VERSION, EL);
+			handle.join().unwrap();
+
+			assert!(matches!(result, Err(ConnectError::InvalidMtuSize { mtu_size: invalid }) if invalid == mtu_size));
+		}
+	}
+}
\ No newline at end of file
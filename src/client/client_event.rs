@@ -0,0 +1,24 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub enum ClientEvent {
+	PacketReceive {
+		packet: Vec<u8>
+	},
+	Connect {
+		server_address: SocketAddr,
+		server_id: u64
+	},
+	Disconnect {
+		reason: String
+	},
+	PacketAck {
+		identifier_ack: u64
+	},
+	PacketLoss {
+		identifier_ack: u64
+	},
+	PingMeasure {
+		latency: Duration
+	}
+}
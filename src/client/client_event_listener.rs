@@ -0,0 +1,49 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::client::ClientEvent;
+
+pub trait ClientEventListener: Send + Sync {
+	fn handle_event(&mut self, event: ClientEvent) {
+		match event {
+			ClientEvent::Connect {
+				server_address,
+				server_id
+			} => self.on_connect(
+				server_address,
+				server_id
+			),
+			ClientEvent::Disconnect {
+				reason
+			} => self.on_disconnect(
+				&reason
+			),
+			ClientEvent::PacketReceive {
+				packet
+			} => self.on_packet_receive(
+				&packet
+			),
+			ClientEvent::PacketAck {
+				identifier_ack
+			} => self.on_packet_ack(
+				identifier_ack
+			),
+			ClientEvent::PacketLoss {
+				identifier_ack
+			} => self.on_packet_loss(
+				identifier_ack
+			),
+			ClientEvent::PingMeasure {
+				latency
+			} => self.on_ping_measure(
+				latency
+			)
+		}
+	}
+
+	fn on_connect(&mut self, server_address: SocketAddr, server_id: u64);
+	fn on_disconnect(&mut self, reason: &str);
+	fn on_packet_receive(&mut self, packet: &[u8]);
+	fn on_packet_ack(&mut self, identifier_ack: u64);
+	fn on_packet_loss(&mut self, identifier_ack: u64);
+	fn on_ping_measure(&mut self, latency: Duration);
+}
@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+use std::io;
+
+#[derive(Debug)]
+pub enum ConnectError {
+	Io(io::Error),
+	/// The server didn't answer the offline handshake in time
+	Timeout,
+	IncompatibleProtocolVersion {
+		server_version: u8
+	},
+	/// The server requires security, which needs the `security` feature
+	RemoteSystemRequiresPubKey,
+	/// A public key was pinned, but the server doesn't have security
+	OurSystemRequiresSecurity,
+	/// The server's public key doesn't match the pinned one or the server couldn't prove it owns it
+	PubKeyMismatch,
+	/// The server reached its connection limit
+	NoFreeIncomingConnections,
+	/// Our IP reached the server's connection limit per IP
+	ConnectionBanned,
+	/// Our IP connected too recently, retrying later may succeed
+	IpRecentlyConnected,
+	/// The server chose an MTU size below the supported minimum or above the requested one
+	InvalidMtuSize {
+		mtu_size: u16
+	}
+}
+
+impl Display for ConnectError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ConnectError::Io(e) => write!(f, "{}", e),
+			ConnectError::Timeout => write!(f, "server didn't respond to the connection request"),
+			ConnectError::IncompatibleProtocolVersion { server_version } => write!(f, "incompatible RakNet protocol version, server uses {}", server_version),
+			ConnectError::RemoteSystemRequiresPubKey => write!(f, "server requires security"),
+			ConnectError::OurSystemRequiresSecurity => write!(f, "server doesn't have security"),
+			ConnectError::PubKeyMismatch => write!(f, "server's public key doesn't match"),
+			ConnectError::NoFreeIncomingConnections => write!(f, "server is full"),
+			ConnectError::ConnectionBanned => write!(f, "too many connections from this IP"),
+			ConnectError::IpRecentlyConnected => write!(f, "connected too recently from this IP"),
+			ConnectError::InvalidMtuSize { mtu_size } => write!(f, "server chose invalid MTU size {}", mtu_size)
+		}
+	}
+}
+
+impl Error for ConnectError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			ConnectError::Io(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl From<io::Error> for ConnectError {
+	fn from(e: io::Error) -> Self {
+		ConnectError::Io(e)
+	}
+}
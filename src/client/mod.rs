@@ -0,0 +1,11 @@
+mod client;
+mod client_event;
+mod client_event_listener;
+mod connect_error;
+mod mtu_discovery;
+
+pub use client::*;
+pub use client_event::ClientEvent;
+pub use client_event_listener::ClientEventListener;
+pub use connect_error::ConnectError;
+pub use mtu_discovery::MtuDiscovery;
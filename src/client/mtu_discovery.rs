@@ -0,0 +1,62 @@
+use std::net::UdpSocket;
+use std::time::Duration;
+use log::debug;
+use crate::client::ConnectError;
+use crate::client::client::handshake;
+use crate::protocol::{OpenConnectionRequest1, OpenConnectionReply1};
+
+/**
+ * Finds the largest MTU size that reaches the server by probing with padded `OpenConnectionRequest1`s,
+ * starting from the biggest candidate size.
+ */
+#[derive(Debug, Clone)]
+pub struct MtuDiscovery {
+	sizes: Vec<u16>,
+	pub attempts: usize, //per size
+	pub timeout: Duration //per attempt
+}
+
+impl MtuDiscovery {
+	//IP header size (20 bytes) + UDP header size (8 bytes)
+	const UDP_OVERHEAD: u16 = 28;
+
+	pub fn new(mut sizes: Vec<u16>) -> Self {
+		sizes.sort_unstable_by(| a, b | b.cmp(a));
+		sizes.dedup();
+		Self {
+			sizes,
+			attempts: 2,
+			timeout: Duration::from_millis(500)
+		}
+	}
+
+	pub fn get_sizes(&self) -> &[u16] {
+		&self.sizes
+	}
+
+	/**
+	 * Returns the reply to the first probe the server answered, its `mtu_size` is the discovered MTU size.
+	 */
+	pub fn discover(&self, udp_socket: &UdpSocket, protocol_version: u8) -> Result<OpenConnectionReply1, ConnectError> {
+		for mtu_size in &self.sizes {
+			if *mtu_size <= Self::UDP_OVERHEAD + OpenConnectionRequest1::HEADER_SIZE as u16 {
+				continue;
+			}
+			match handshake(udp_socket, &OpenConnectionRequest1 {
+				offline_message: Default::default(),
+				protocol: protocol_version,
+				mtu_size: *mtu_size - Self::UDP_OVERHEAD
+			}, self.attempts, self.timeout) {
+				Err(ConnectError::Timeout) => debug!("No reply with MTU size {}, trying a smaller size", mtu_size),
+				result => return result
+			}
+		}
+		Err(ConnectError::Timeout)
+	}
+}
+
+impl Default for MtuDiscovery {
+	fn default() -> Self {
+		Self::new(vec![1492, 1200, 576])
+	}
+}
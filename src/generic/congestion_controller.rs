@@ -0,0 +1,18 @@
+/**
+ * Decides how many bytes a `SendReliabilityLayer` may have in flight.
+ * `is_continuous_send` tells whether datagrams are waiting for the window to open,
+ * controllers shouldn't grow the window while the sender doesn't use it.
+ */
+pub trait CongestionController: Send + Sync {
+	/**
+	 * Bytes which may be sent right now, given the bytes sent but not acknowledged yet
+	 */
+	fn get_transmission_bandwidth(&self, unacknowledged_bytes: usize) -> usize;
+	fn on_send(&mut self, sequence_number: u32, bytes: usize);
+	fn on_ack(&mut self, sequence_number: u32, is_continuous_send: bool);
+	fn on_nack(&mut self, sequence_number: u32, is_continuous_send: bool);
+	/**
+	 * The datagram wasn't acknowledged in time
+	 */
+	fn on_resend(&mut self, sequence_number: u32, is_continuous_send: bool);
+}
@@ -0,0 +1,22 @@
+/**
+ * Protects the datagrams, ACKs and NACKs of a connection once the handshake agreed on keys
+ */
+pub trait DatagramCipher: Send + Sync {
+	fn encrypt(&self, payload: &[u8]) -> Vec<u8>;
+	/**
+	 * Returns None if the payload was tampered with, encrypted with another key or replayed
+	 */
+	fn decrypt(&self, payload: &[u8]) -> Option<Vec<u8>>;
+
+	/**
+	 * Bytes an encrypted packet is larger than its plaintext, connections take it off their MTU size
+	 */
+	fn overhead(&self) -> usize;
+}
+
+/**
+ * MTU size left for the plaintext of a connection with the given cipher
+ */
+pub(crate) fn datagram_mtu_size(mtu_size: usize, cipher: Option<&dyn DatagramCipher>) -> usize {
+	mtu_size - cipher.map_or(0, | cipher | cipher.overhead())
+}
@@ -0,0 +1,57 @@
+use std::fmt::{Debug, Formatter};
+use x25519_dalek::{StaticSecret, PublicKey};
+
+/**
+ * X25519 key pair. Servers advertise the public key in `OpenConnectionReply1`, clients can pin it.
+ */
+#[derive(Clone)]
+pub struct KeyPair {
+	secret_key: StaticSecret,
+	public_key: [u8; 32]
+}
+
+impl KeyPair {
+	pub fn generate() -> Self {
+		let mut secret_key = [0; 32];
+		getrandom::getrandom(&mut secret_key).expect("no random source for the key pair");
+		Self::from_secret_key(secret_key)
+	}
+
+	pub fn from_secret_key(secret_key: [u8; 32]) -> Self {
+		let secret_key = StaticSecret::from(secret_key);
+		let public_key = PublicKey::from(&secret_key).to_bytes();
+		Self {
+			secret_key,
+			public_key
+		}
+	}
+
+	pub fn get_public_key(&self) -> [u8; 32] {
+		self.public_key
+	}
+
+	/**
+	 * Store it to keep the public key stable across restarts
+	 */
+	pub fn get_secret_key(&self) -> [u8; 32] {
+		self.secret_key.to_bytes()
+	}
+
+	/**
+	 * Returns None for public keys of low order, which would make the shared secret predictable
+	 */
+	pub(crate) fn diffie_hellman(&self, public_key: &[u8; 32]) -> Option<[u8; 32]> {
+		let shared_secret = self.secret_key.diffie_hellman(&PublicKey::from(*public_key));
+		if shared_secret.was_contributory() {
+			Some(shared_secret.to_bytes())
+		} else {
+			None
+		}
+	}
+}
+
+impl Debug for KeyPair {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("KeyPair").field("public_key", &self.public_key).finish_non_exhaustive()
+	}
+}
@@ -1,7 +1,30 @@
+mod congestion_controller;
+mod datagram_cipher;
+#[cfg(feature = "security")]
+mod key_pair;
+mod ordering_slot;
 mod receive_reliability_layer;
 mod reliable_cache_entry;
+mod rtt_estimator;
 mod send_reliability_layer;
+#[cfg(feature = "security")]
+mod session_cipher;
+mod sliding_window;
+mod split_packet;
+mod u24;
 
+pub use congestion_controller::CongestionController;
+pub use datagram_cipher::DatagramCipher;
+pub(crate) use datagram_cipher::datagram_mtu_size;
+#[cfg(feature = "security")]
+pub use key_pair::KeyPair;
+pub use ordering_slot::OrderingSlot;
 pub use receive_reliability_layer::ReceiveReliabilityLayer;
 pub use reliable_cache_entry::ReliableCacheEntry;
-pub use send_reliability_layer::SendReliabilityLayer;
\ No newline at end of file
+pub use rtt_estimator::RttEstimator;
+pub use send_reliability_layer::SendReliabilityLayer;
+#[cfg(feature = "security")]
+pub use session_cipher::SessionCipher;
+pub use sliding_window::SlidingWindow;
+pub use split_packet::SplitPacket;
+pub use u24::{U24_MODULUS, U24_MASK, u24_add, u24_distance, u24_is_before};
\ No newline at end of file
@@ -0,0 +1,11 @@
+use crate::protocol::EncapsulatedPacket;
+
+/**
+ * Packets waiting for one order index of an order channel: the ordered packet with that index and the sequenced
+ * packets which were sent right before it
+ */
+#[derive(Default)]
+pub struct OrderingSlot {
+	pub ordered: Option<Box<EncapsulatedPacket>>,
+	pub sequenced: Vec<Box<EncapsulatedPacket>>
+}
@@ -1,39 +1,65 @@
-use std::collections::{VecDeque, HashMap};
-use crate::protocol::{EncapsulatedPacket, PacketReliability, Datagram, ACK, NACK, PacketImpl, Packet};
+use std::collections::{VecDeque, HashMap, BTreeSet, BTreeMap};
+use crate::protocol::{EncapsulatedPacket, PacketReliability, Datagram, ACK, NACK, AcknowledgePacket, PacketImpl, Packet};
+use crate::generic::{SplitPacket, OrderingSlot, u24_add, u24_distance, u24_is_before, U24_MODULUS};
 use log::debug;
-use std::ops::RangeInclusive;
-use std::iter::repeat;
+use std::time::{Duration, Instant};
 
 pub struct ReceiveReliabilityLayer<'a> {
 	on_recv: Box<dyn Fn(&mut EncapsulatedPacket) -> () + Send + Sync + 'a>,
 
 	send_packet: Box<dyn Fn(Packet) -> () + Send + Sync + 'a>,
 
-	window_start: usize,
-	window_end: usize,
+	window_start: u32, //sequence numbers up to window_size after it are accepted
 
-	ack_queue: Vec<Option<u32>>,
-	nack_queue: Vec<Option<u32>>,
+	received_window: VecDeque<bool>, //datagrams which were received or NACKed, front is window_start
 
-	reliable_window_start: usize,
-	reliable_window_end: usize,
+	highest_seq_number: Option<u32>,
 
-	reliable_window: Vec<bool>, //originally hashmap; false = not set / true = set
+	ack_queue: BTreeSet<u32>,
+	ack_queue_since: Option<Instant>, //when the oldest queued ACK was added
+	nack_queue: BTreeMap<u32, Instant>, //missing datagrams and when the gap was detected
 
-	receive_ordered_index: [usize; PacketReliability::MAX_ORDER_CHANNELS],
-	receive_sequenced_highest_index: [usize; PacketReliability::MAX_ORDER_CHANNELS],
-	receive_ordered_packets: [VecDeque<Option<Box<EncapsulatedPacket>>>; PacketReliability::MAX_ORDER_CHANNELS],
+	ack_delay: Duration,
+	ack_batch_size: usize,
+	nack_delay: Duration,
 
-	split_packets: HashMap<u16, Vec<Option<Box<EncapsulatedPacket>>>>,
+	reliable_window_start: u32,
+
+	reliable_window: VecDeque<bool>, //originally hashmap; false = not set / true = set, front is reliable_window_start
+
+	receive_ordered_index: [u32; PacketReliability::MAX_ORDER_CHANNELS],
+	receive_sequenced_highest_index: [u32; PacketReliability::MAX_ORDER_CHANNELS],
+	receive_ordered_packets: [VecDeque<OrderingSlot>; PacketReliability::MAX_ORDER_CHANNELS], //front is the expected order index
+	buffered_sequenced_count: [usize; PacketReliability::MAX_ORDER_CHANNELS], //sequenced packets waiting in receive_ordered_packets
+
+	split_packets: HashMap<u16, SplitPacket>,
+
+	split_buffer_size: usize, //bytes allocated by split_packets
+
+	max_split_buffer_size: usize,
+
+	split_timeout: Duration,
 
 	max_split_packet_part_count: usize,
 
 	max_concurrent_split_packets: usize,
+
+	window_size: usize,
+
+	mtu_size: usize
 }
 
 impl<'a> ReceiveReliabilityLayer<'a> {
 
 	pub const WINDOW_SIZE: usize = 2048;
+	pub const DEFAULT_MAX_SPLIT_BUFFER_SIZE: usize = 1024 * 1024;
+	pub const DEFAULT_SPLIT_TIMEOUT: Duration = Duration::from_secs(10);
+	pub const DEFAULT_MTU_SIZE: usize = 576;
+	pub const DEFAULT_ACK_DELAY: Duration = Duration::from_millis(10);
+	pub const DEFAULT_ACK_BATCH_SIZE: usize = 32;
+	pub const DEFAULT_NACK_DELAY: Duration = Duration::from_millis(10);
+	/// Sequenced packets held back for a single missing ordered packet, the whole channel holds at most the window size
+	pub const MAX_SEQUENCED_PER_SLOT: usize = 64;
 
 	pub fn new(
 		on_recv: impl Fn(&mut EncapsulatedPacket) -> () + Send + Sync + 'a,
@@ -52,32 +78,118 @@ impl<'a> ReceiveReliabilityLayer<'a> {
 		send_packet: impl Fn(Packet) -> () + Send + Sync + 'a,
 		max_split_packet_part_count: usize,
 		max_concurrent_split_packets: usize
+	) -> Self {
+		Self::with_limits(
+			on_recv,
+			send_packet,
+			max_split_packet_part_count,
+			max_concurrent_split_packets,
+			Self::WINDOW_SIZE
+		)
+	}
+
+	pub fn with_limits(
+		on_recv: impl Fn(&mut EncapsulatedPacket) -> () + Send + Sync + 'a,
+		send_packet: impl Fn(Packet) -> () + Send + Sync + 'a,
+		max_split_packet_part_count: usize,
+		max_concurrent_split_packets: usize,
+		window_size: usize
 	) -> Self {
 		Self {
 			on_recv: Box::new(on_recv),
 			send_packet: Box::new(send_packet),
 			window_start: 0,
-			window_end: Self::WINDOW_SIZE,
+			received_window: VecDeque::from(vec![false; window_size]),
+			highest_seq_number: None,
 			ack_queue: Default::default(),
+			ack_queue_since: None,
 			nack_queue: Default::default(),
+			ack_delay: Self::DEFAULT_ACK_DELAY,
+			ack_batch_size: Self::DEFAULT_ACK_BATCH_SIZE,
+			nack_delay: Self::DEFAULT_NACK_DELAY,
 			reliable_window_start: 0,
-			reliable_window_end: Self::WINDOW_SIZE,
-			reliable_window: vec![false; Self::WINDOW_SIZE],
+			reliable_window: VecDeque::from(vec![false; window_size]),
 			receive_ordered_index: Default::default(),
 			receive_sequenced_highest_index: Default::default(),
 			receive_ordered_packets: Default::default(),
+			buffered_sequenced_count: Default::default(),
 			split_packets: Default::default(),
+			split_buffer_size: 0,
+			max_split_buffer_size: Self::DEFAULT_MAX_SPLIT_BUFFER_SIZE,
+			split_timeout: Self::DEFAULT_SPLIT_TIMEOUT,
 			max_split_packet_part_count,
-			max_concurrent_split_packets
+			max_concurrent_split_packets,
+			window_size,
+			mtu_size: Self::DEFAULT_MTU_SIZE
 		}
 	}
 
-	fn window_range(&self) -> RangeInclusive<usize> {
-		self.window_start..=self.window_end
+	pub fn set_max_split_part_count(&mut self, max_split_part_count: usize) {
+		self.max_split_packet_part_count = max_split_part_count;
 	}
 
-	fn reliable_window_range(&self) -> RangeInclusive<usize> {
-		self.reliable_window_start..=self.reliable_window_end
+	/**
+	 * Bytes all incomplete split packets may buffer together, parts exceeding it are dropped
+	 */
+	pub fn set_max_split_buffer_size(&mut self, max_split_buffer_size: usize) {
+		self.max_split_buffer_size = max_split_buffer_size;
+	}
+
+	/**
+	 * Incomplete split packets are discarded after this time
+	 */
+	pub fn set_split_timeout(&mut self, split_timeout: Duration) {
+		self.split_timeout = split_timeout;
+	}
+
+	/**
+	 * ACKs and NACKs which don't fit in a single datagram of this size are split
+	 */
+	pub fn set_mtu_size(&mut self, mtu_size: usize) {
+		self.mtu_size = mtu_size;
+	}
+
+	/**
+	 * ACKs are held back for at most this time, unless they can be sent along with outgoing datagrams
+	 */
+	pub fn set_ack_delay(&mut self, ack_delay: Duration) {
+		self.ack_delay = ack_delay;
+	}
+
+	/**
+	 * ACKs are sent right away once this many datagrams are waiting for one
+	 */
+	pub fn set_ack_batch_size(&mut self, ack_batch_size: usize) {
+		self.ack_batch_size = ack_batch_size;
+	}
+
+	/**
+	 * Reordering threshold, a missing datagram is only NACKed once the gap lasted this long
+	 */
+	pub fn set_nack_delay(&mut self, nack_delay: Duration) {
+		self.nack_delay = nack_delay;
+	}
+
+	pub fn get_split_buffer_size(&self) -> usize {
+		self.split_buffer_size
+	}
+
+	/**
+	 * Sequenced packets of the order channel waiting for an ordered packet sent before them
+	 */
+	pub fn get_buffered_sequenced_count(&self, order_channel: usize) -> usize {
+		self.buffered_sequenced_count[order_channel]
+	}
+
+	/**
+	 * Expects the counters of the peer to start at `index`, so tests can cross the 24-bit wrap point
+	 */
+	#[cfg(test)]
+	pub(crate) fn skip_to_index(&mut self, index: u32) {
+		self.window_start = index;
+		self.highest_seq_number = None;
+		self.reliable_window_start = index;
+		self.receive_ordered_index = [index; PacketReliability::MAX_ORDER_CHANNELS];
 	}
 
 	fn handle_encapsulated_packet_route(&mut self, packet: &mut EncapsulatedPacket) {
@@ -85,12 +197,15 @@ impl<'a> ReceiveReliabilityLayer<'a> {
 	}
 
 	fn handle_split(&mut self, packet: EncapsulatedPacket) -> Option<EncapsulatedPacket> {
-		let split_info = packet.split_info.as_ref().unwrap();
+		let split_info = match packet.split_info.as_ref() {
+			Some(split_info) => split_info,
+			None => return Some(packet)
+		};
 		let total_parts = split_info.get_total_part_count() as usize;
 		let part_index = split_info.get_part_index() as usize;
 
 		if
-			total_parts >= self.max_split_packet_part_count ||
+			total_parts > self.max_split_packet_part_count ||
 			part_index >= total_parts
 		{
 			debug!(
@@ -102,65 +217,71 @@ impl<'a> ReceiveReliabilityLayer<'a> {
 		}
 
 		let split_id = split_info.get_id();
-		if self.split_packets.get(&split_id).is_none() {
-			if self.split_packets.len() >= self.max_concurrent_split_packets {
-				debug!("Ignored split packet part because reached concurrent split packet limit of self.max_concurrent_split_packets");
+		let (size, growth) = if let Some(split) = self.split_packets.get(&split_id) {
+			if split.get_part_count() != total_parts {
+				debug!("Wrong split count {} for split packet {}, expected {}", total_parts, split_id, split.get_part_count());
 				return None;
 			}
-			self.split_packets.insert(split_id, vec![None; total_parts]);
-		} else if self.split_packets[&split_id].len() != total_parts {
-			debug!("Wrong split count {} for split packet $splitId, expected {}", total_parts, self.split_packets[&split_id].len());
+			(split.get_size(), split.get_growth(part_index, packet.buffer.len()))
+		} else if self.split_packets.len() >= self.max_concurrent_split_packets {
+			debug!("Ignored split packet part because reached concurrent split packet limit of {}", self.max_concurrent_split_packets);
+			return None;
+		} else {
+			(0, SplitPacket::get_initial_size(total_parts, part_index, packet.buffer.len()))
+		};
+		if self.split_buffer_size + growth > self.max_split_buffer_size {
+			debug!("Ignored split packet part because buffered split packets would exceed {} bytes", self.max_split_buffer_size);
 			return None;
 		}
 
-		self.split_packets.get_mut(&split_id).unwrap()[part_index] = Some(Box::new(packet)); //wtf rust
-
-		let mut total_len = 0;
-		for packet in &self.split_packets[&split_id] {
-			if let Some(packet) = packet {
-				total_len += packet.buffer.len();
-			} else {
-				return None;
-			}
+		let split = self.split_packets.entry(split_id).or_insert_with(|| SplitPacket::new(total_parts));
+		if !split.add_part(part_index, packet) {
+			debug!("Ignored duplicate or malformed part {} of split packet {}", part_index, split_id);
+		} else {
+			self.split_buffer_size += split.get_size() - size;
 		}
-
-		let split_packets = self.split_packets.remove(&split_id).unwrap();
-
-		let packet = split_packets[part_index].as_ref().unwrap();
-
-		let mut pk = EncapsulatedPacket::default();
-		pk.buffer = Vec::with_capacity(total_len);
-
-		pk.reliability = packet.reliability;
-		pk.message_index = packet.message_index;
-		pk.sequence_index = packet.sequence_index;
-		pk.order_index = packet.order_index;
-		pk.order_channel = packet.order_channel;
-
-		for packet in split_packets {
-			pk.buffer.extend_from_slice(&packet.unwrap().buffer);
+		if split.is_empty() {
+			self.split_packets.remove(&split_id);
+			return None;
+		}
+		if !split.is_complete() {
+			return None;
 		}
 
-		Some(pk)
+		let split = self.split_packets.remove(&split_id).unwrap();
+		self.split_buffer_size -= split.get_size();
+		Some(split.into_packet())
+	}
+
+	fn expire_split_packets(&mut self) {
+		let now = Instant::now();
+		let split_timeout = self.split_timeout;
+		let mut expired_size = 0;
+		self.split_packets.retain(| split_id, split | {
+			if now.duration_since(split.timestamp) < split_timeout {
+				return true;
+			}
+			debug!("Discarded incomplete split packet {} after {:?}", split_id, split_timeout);
+			expired_size += split.get_size();
+			false
+		});
+		self.split_buffer_size -= expired_size;
 	}
 
 	pub(crate) fn handle_encapsulated_packet(&mut self, mut packet: EncapsulatedPacket) {
-		if let Some(message_index) = packet.message_index.map(| i | i as usize) {
-			if
-				!self.reliable_window_range().contains(&message_index) ||
-				self.reliable_window[message_index % Self::WINDOW_SIZE]
-			{
+		if let Some(message_index) = packet.message_index {
+			let offset = u24_distance(self.reliable_window_start, message_index) as usize;
+			if offset >= self.window_size || self.reliable_window[offset] {
+				//already received, or too old or new to tell
 				return;
 			}
 
-			self.reliable_window[message_index % Self::WINDOW_SIZE] = true;
+			self.reliable_window[offset] = true;
 
-			if message_index == self.reliable_window_start {
-				while self.reliable_window[self.reliable_window_start % Self::WINDOW_SIZE] {
-					self.reliable_window[self.reliable_window_start % Self::WINDOW_SIZE] = false;
-					self.reliable_window_start += 1;
-					self.reliable_window_end += 1;
-				}
+			while self.reliable_window[0] {
+				self.reliable_window.pop_front();
+				self.reliable_window.push_back(false);
+				self.reliable_window_start = u24_add(self.reliable_window_start, 1);
 			}
 		}
 
@@ -178,121 +299,729 @@ impl<'a> ReceiveReliabilityLayer<'a> {
 			return;
 		}
 
-		if packet.reliability.is_sequenced() {
-			let sequence_index = packet.sequence_index.unwrap() as usize;
-			let order_channel = packet.order_channel.unwrap() as usize;
-			let order_index = packet.order_index.unwrap() as usize;
-			if
-				sequence_index < self.receive_sequenced_highest_index[order_channel] ||
-				order_index < self.receive_ordered_index[order_channel]
-			{
-				//too old sequenced packet, discard it
-				return;
-			}
-
-			self.receive_sequenced_highest_index[order_channel] = sequence_index + 1;
+		if packet.reliability.is_sequenced() || packet.reliability.is_ordered() {
+			self.handle_ordered(packet);
+		} else {
+			//not ordered or sequenced
 			self.handle_encapsulated_packet_route(&mut packet);
-		} else if packet.reliability.is_ordered() {
-			let order_channel = packet.order_channel.unwrap() as usize;
-			let order_index = packet.order_index.unwrap() as usize;
-			if order_index == self.receive_ordered_index[order_channel] {
-				//this is the packet we expected to get next
-				//Any ordered packet resets the sequence index to zero, so that sequenced packets older than this ordered
-				//one get discarded. Sequenced packets also include (but don't increment) the order index, so a sequenced
-				//packet with an order index less than this will get discarded
-				self.receive_sequenced_highest_index[order_index] = 0;
-				self.receive_ordered_index[order_channel] = order_index + 1;
-
-				self.handle_encapsulated_packet_route(&mut packet);
-				while let Some(Some(_)) = self.receive_ordered_packets[order_channel].front_mut() {
-					let mut pk = self.receive_ordered_packets[order_channel].pop_front().unwrap().unwrap();
-					self.handle_encapsulated_packet_route(pk.as_mut());
-					self.receive_ordered_index[order_channel] += 1;
+		}
+	}
+
+	fn handle_ordered(&mut self, mut packet: EncapsulatedPacket) {
+		let order_channel = packet.order_channel.unwrap() as usize;
+		let order_index = packet.order_index.unwrap();
+		let expected_index = self.receive_ordered_index[order_channel];
+		if u24_is_before(order_index, expected_index) {
+			//older than the last delivered ordered packet, discard it
+			return;
+		}
+
+		let distance = u24_distance(expected_index, order_index) as usize;
+		if distance >= self.window_size {
+			debug!("Ignored packet too far ahead on order channel {} (order index {}, expected {})", order_channel, order_index, expected_index);
+			return;
+		}
+		if distance > 0 {
+			//sequenced packets are also held back until every ordered packet sent before them was delivered
+			let slots = &mut self.receive_ordered_packets[order_channel];
+			if slots.len() <= distance {
+				slots.resize_with(distance + 1, Default::default);
+			}
+			let slot = &mut slots[distance];
+			if packet.reliability.is_sequenced() {
+				let sequence_index = packet.sequence_index;
+				if
+					self.buffered_sequenced_count[order_channel] >= self.window_size ||
+					slot.sequenced.len() >= Self::MAX_SEQUENCED_PER_SLOT
+				{
+					debug!("Ignored sequenced packet because too many are waiting on order channel {} (order index {})", order_channel, order_index);
+				} else if !slot.sequenced.iter().any(| pk | pk.sequence_index == sequence_index) {
+					slot.sequenced.push(Box::new(packet));
+					self.buffered_sequenced_count[order_channel] += 1;
 				}
-			} else if order_index > self.receive_ordered_index[order_channel] {
-				self.receive_ordered_packets[order_channel].extend(repeat(None).take(order_index - order_channel - 1));
-				self.receive_ordered_packets[order_channel].push_back(Some(Box::new(packet)));
-			} else {
-				//duplicate/already received packet
+			} else if slot.ordered.is_none() {
+				slot.ordered = Some(Box::new(packet));
 			}
+			return;
+		}
+
+		if packet.reliability.is_sequenced() {
+			self.handle_sequenced(packet);
 		} else {
-			//not ordered or sequenced
+			//this is the packet we expected to get next
 			self.handle_encapsulated_packet_route(&mut packet);
+			self.advance_order_channel(order_channel);
+		}
+	}
+
+	fn handle_sequenced(&mut self, mut packet: EncapsulatedPacket) {
+		let order_channel = packet.order_channel.unwrap() as usize;
+		let sequence_index = packet.sequence_index.unwrap();
+		if u24_is_before(sequence_index, self.receive_sequenced_highest_index[order_channel]) {
+			//too old sequenced packet, discard it
+			return;
+		}
+
+		self.receive_sequenced_highest_index[order_channel] = u24_add(sequence_index, 1);
+		self.handle_encapsulated_packet_route(&mut packet);
+	}
+
+	/**
+	 * Moves past a delivered ordered packet and delivers the packets which were waiting for it
+	 */
+	fn advance_order_channel(&mut self, order_channel: usize) {
+		loop {
+			//Any ordered packet resets the sequence index to zero, so that sequenced packets older than this ordered
+			//one get discarded. Sequenced packets also include (but don't increment) the order index, so a sequenced
+			//packet with an order index less than this will get discarded
+			self.receive_sequenced_highest_index[order_channel] = 0;
+			self.receive_ordered_index[order_channel] = u24_add(self.receive_ordered_index[order_channel], 1);
+
+			let slots = &mut self.receive_ordered_packets[order_channel];
+			slots.pop_front();
+			let slot = match slots.front_mut() {
+				Some(slot) => std::mem::take(slot),
+				None => return
+			};
+
+			self.buffered_sequenced_count[order_channel] -= slot.sequenced.len();
+			let mut sequenced = slot.sequenced;
+			if let Some(first) = sequenced.first() {
+				//serial order, counted from half the index space before the first buffered packet so it stays a total order
+				let base = u24_add(first.sequence_index.unwrap(), U24_MODULUS / 2);
+				sequenced.sort_by_key(| pk | u24_distance(base, pk.sequence_index.unwrap()));
+				//all of them were sent after the ordered packet which reset the index, the oldest may be just before the wrap
+				self.receive_sequenced_highest_index[order_channel] = sequenced[0].sequence_index.unwrap();
+			}
+			for pk in sequenced {
+				self.handle_sequenced(*pk);
+			}
+			match slot.ordered {
+				Some(mut pk) => self.handle_encapsulated_packet_route(&mut pk),
+				None => return
+			}
 		}
 	}
 
 	pub fn on_datagram(&mut self, packet: &mut Datagram) {
-		let sequence_number = packet.sequence_number.unwrap() as usize;
+		let sequence_number = packet.sequence_number.unwrap();
+		let offset = u24_distance(self.window_start, sequence_number) as usize;
+		if offset >= self.window_size || self.received_window[offset] {
+			debug!("Received duplicate or out-of-window packet (sequence number {}, window {}-{})", sequence_number, self.window_start, u24_add(self.window_start, self.window_size as u32 - 1));
+			return;
+		}
+
+		self.received_window[offset] = true;
+		self.nack_queue.remove(&sequence_number);
+		if self.ack_queue.is_empty() {
+			self.ack_queue_since = Some(Instant::now());
+		}
+		self.ack_queue.insert(sequence_number);
+
+		//we got a gap - later packets arrived before earlier ones did
+		//we add the earlier ones to the NACK queue
+		//if the missing packets arrive before the reordering threshold passes, they'll be removed from the NACK queue
+		let gap_start = match self.highest_seq_number {
+			Some(highest_seq_number) if !u24_is_before(highest_seq_number, self.window_start) => u24_distance(self.window_start, highest_seq_number) as usize + 1,
+			_ => 0
+		};
+		let now = Instant::now();
+		for i in gap_start..offset {
+			if !self.received_window[i] {
+				self.nack_queue.insert(u24_add(self.window_start, i as u32), now);
+			}
+		}
+		if self.highest_seq_number.is_none_or(| x | u24_is_before(x, sequence_number)) {
+			self.highest_seq_number = Some(sequence_number);
+		}
+
+		self.advance_window();
+
+		for pk in packet.packets.drain(..) {
+			self.handle_encapsulated_packet(*pk);
+		}
+	}
+
+	/**
+	 * Shifts the receive window past the datagrams at its start which were received or NACKed
+	 */
+	fn advance_window(&mut self) {
+		while self.received_window[0] {
+			self.received_window.pop_front();
+			self.received_window.push_back(false);
+			self.window_start = u24_add(self.window_start, 1);
+		}
+	}
+
+	/**
+	 * `is_sending` tells whether datagrams were sent to the peer this tick, pending ACKs are sent along with them
+	 */
+	pub fn update(&mut self, is_sending: bool) {
+		self.expire_split_packets();
+
+		let now = Instant::now();
+		//IP header (20 bytes) + UDP header (8 bytes) + RakNet weird (8 bytes) = 36 bytes
+		let max_size = self.mtu_size - 36;
+
 		if
-			self.window_range().contains(&sequence_number) ||
-			self.ack_queue.get(sequence_number).map(| x | x.is_some()).unwrap_or(false)
+			!self.ack_queue.is_empty() && (
+				is_sending ||
+				self.ack_queue.len() >= self.ack_batch_size ||
+				self.ack_queue_since.is_some_and(| since | now.duration_since(since) >= self.ack_delay)
+			)
 		{
-			debug!("Received duplicate or out-of-window packet (sequence number $packet->seqNumber, window {}-{})", self.window_start, self.window_end);
-			return;
+			let sequence_numbers: Vec<u32> = self.ack_queue.iter().cloned().collect();
+			for acknowledge in AcknowledgePacket::split(&sequence_numbers, max_size) {
+				(self.send_packet)(ACK { acknowledge }.into_dyn());
+			}
+			self.ack_queue.clear();
+			self.ack_queue_since = None;
+		}
+
+		let nack_delay = self.nack_delay;
+		let lost: Vec<u32> = self.nack_queue.iter()
+			.filter(| (_, detected) | now.duration_since(**detected) >= nack_delay)
+			.map(| (sequence_number, _) | *sequence_number)
+			.collect();
+		if !lost.is_empty() {
+			for sequence_number in &lost {
+				self.nack_queue.remove(sequence_number);
+				//the peer resends the packets with a new sequence number, so we stop waiting for this one
+				let offset = u24_distance(self.window_start, *sequence_number) as usize;
+				if offset < self.window_size {
+					self.received_window[offset] = true;
+				}
+			}
+			self.advance_window();
+			for acknowledge in AcknowledgePacket::split(&lost, max_size) {
+				(self.send_packet)(NACK { acknowledge }.into_dyn());
+			}
+		}
+	}
+
+	pub fn needs_update(&self) -> bool {
+		!self.ack_queue.is_empty() || !self.nack_queue.is_empty()
+	}
+
+}
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::thread::sleep;
+	use std::time::Duration;
+	use parking_lot::Mutex;
+	use std::convert::{TryFrom, TryInto};
+	use crate::generic::{ReceiveReliabilityLayer, SendReliabilityLayer, u24_add, U24_MASK};
+	use crate::protocol::{EncapsulatedPacket, PacketReliability, SplitPacketInfo, Datagram, Packet, EncodePacket, DecodePacket, ACK, NACK};
+
+	/**
+	 * Returns the encapsulated packets the send layer puts on the wire
+	 */
+	fn send(packets: Vec<EncapsulatedPacket>, max_split_part_count: usize) -> Vec<EncapsulatedPacket> {
+		let sent: Arc<Mutex<Vec<(u32, Vec<EncapsulatedPacket>)>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::new(
+			400,
+			move | datagram | sent_clone.lock().push((
+				datagram.sequence_number.unwrap(),
+				datagram.packets.iter().map(| x | *x.clone()).collect()
+			)),
+			| _ | {},
+			| _ | {}
+		);
+		layer.set_max_split_part_count(max_split_part_count);
+		for packet in packets {
+			layer.add_encapsulated_to_queue(packet, true);
+		}
+
+		let mut packets = vec![];
+		loop {
+			let datagrams: Vec<(u32, Vec<EncapsulatedPacket>)> = sent.lock().drain(..).collect();
+			if datagrams.is_empty() {
+				return packets;
+			}
+			//acknowledge everything, the congestion window only lets a few datagrams through at once
+			let mut ack = ACK::default();
+			for (seq, datagram_packets) in datagrams {
+				ack.packets.push(seq);
+				packets.extend(datagram_packets);
+			}
+			layer.on_ack(&ack);
+		}
+	}
+
+	fn split(payload: &[u8], max_split_part_count: usize) -> Vec<EncapsulatedPacket> {
+		let mut packet = EncapsulatedPacket::default();
+		packet.reliability = PacketReliability::ReliableOrdered;
+		packet.order_channel = Some(0);
+		packet.buffer = payload.to_vec();
+		send(vec![packet], max_split_part_count)
+	}
+
+	#[test]
+	fn split_reassembly() {
+		let payload: Vec<u8> = (0..10000).map(| x | x as u8).collect();
+		let packets = split(&payload, 128);
+		assert_eq!(packets.len(), 30);
+		for (i, packet) in packets.iter().enumerate() {
+			let split_info = packet.split_info.as_ref().unwrap();
+			assert_eq!(split_info.get_part_index() as usize, i);
+			assert_eq!(split_info.get_total_part_count(), 30);
+			assert_eq!(packet.message_index, Some(i as u32));
+			assert_eq!(packet.order_index, Some(0));
+		}
+
+		let mut layer = ReceiveReliabilityLayer::new(| _ | {}, | _ | {});
+		let mut reassembled = None;
+		for packet in packets.into_iter().rev() {
+			assert!(reassembled.is_none());
+			reassembled = layer.handle_split(packet);
+		}
+		let reassembled = reassembled.unwrap();
+		assert_eq!(reassembled.buffer, payload);
+		assert_eq!(reassembled.order_index, Some(0));
+		assert!(reassembled.split_info.is_none());
+	}
+
+	#[test]
+	fn split_part_limit() {
+		let payload = vec![0xfe; 340 * 200];
+		assert!(split(&payload, 128).is_empty());
+
+		let packets = split(&payload, 200);
+		assert_eq!(packets.len(), 200);
+		let mut layer = ReceiveReliabilityLayer::with_split_limit(| _ | {}, | _ | {}, 128, 4);
+		assert!(packets.iter().all(| x | layer.handle_split(x.clone()).is_none()));
+
+		layer.set_max_split_part_count(200);
+		let reassembled: Vec<EncapsulatedPacket> = packets.into_iter().filter_map(| x | layer.handle_split(x)).collect();
+		assert_eq!(reassembled.len(), 1);
+		assert_eq!(reassembled[0].buffer, payload);
+	}
+
+	#[test]
+	fn sequenced_indices() {
+		let sent: Arc<Mutex<Vec<EncapsulatedPacket>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | sent_clone.lock().extend(datagram.packets.iter().map(| x | *x.clone())),
+			| _ | {},
+			| _ | {}
+		);
+		for reliability in [PacketReliability::UnreliableSequenced, PacketReliability::UnreliableSequenced, PacketReliability::ReliableOrdered, PacketReliability::UnreliableSequenced] {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = reliability;
+			packet.order_channel = Some(0);
+			packet.buffer = vec![0xfe; 10];
+			layer.add_encapsulated_to_queue(packet, false);
+		}
+		layer.update();
+		let indices: Vec<(Option<u32>, Option<u32>)> = sent.lock().iter().map(| x | (x.order_index, x.sequence_index)).collect();
+		assert_eq!(indices, vec![(Some(0), Some(0)), (Some(0), Some(1)), (Some(0), None), (Some(1), Some(0))]);
+	}
+
+	fn part(split_id: u16, part_index: u32, part_count: u32, length: usize) -> EncapsulatedPacket {
+		let mut packet = EncapsulatedPacket::default();
+		packet.split_info = Some(SplitPacketInfo::new(split_id, part_index, part_count));
+		packet.buffer = vec![0xfe; length];
+		packet
+	}
+
+	#[test]
+	fn split_timeout() {
+		let mut layer = ReceiveReliabilityLayer::with_split_limit(| _ | {}, | _ | {}, 128, 4);
+		layer.set_split_timeout(Duration::from_millis(20));
+		//the first parts of a few splits occupy every slot
+		for split_id in 0..5 {
+			assert!(layer.handle_split(part(split_id, 0, 2, 100)).is_none());
+		}
+		assert!(layer.handle_split(part(4, 1, 2, 100)).is_none());
+		assert_eq!(layer.get_split_buffer_size(), 408);
+
+		sleep(Duration::from_millis(30));
+		layer.update(false);
+		assert_eq!(layer.get_split_buffer_size(), 0);
+		assert!(layer.handle_split(part(4, 0, 2, 100)).is_none());
+		assert!(layer.handle_split(part(4, 1, 2, 100)).is_some());
+	}
+
+	#[test]
+	fn split_buffer_size() {
+		let mut layer = ReceiveReliabilityLayer::new(| _ | {}, | _ | {});
+		layer.set_max_split_buffer_size(1020);
+		//the first part reserves room for all parts but the last one and a flag per part
+		assert!(layer.handle_split(part(0, 0, 10, 100)).is_none());
+		assert_eq!(layer.get_split_buffer_size(), 910);
+		assert!(layer.handle_split(part(1, 0, 2, 200)).is_none());
+		assert_eq!(layer.get_split_buffer_size(), 910);
+		assert!(layer.handle_split(part(1, 1, 2, 50)).is_none());
+		assert_eq!(layer.get_split_buffer_size(), 962);
+		//the flags alone exceed the budget
+		assert!(layer.handle_split(part(2, 0, 1000, 0)).is_none());
+		assert_eq!(layer.get_split_buffer_size(), 962);
+
+		for part_index in 1..9 {
+			assert!(layer.handle_split(part(0, part_index, 10, 100)).is_none());
+		}
+		assert_eq!(layer.handle_split(part(0, 9, 10, 50)).unwrap().buffer.len(), 950);
+		assert_eq!(layer.handle_split(part(1, 0, 2, 100)).unwrap().buffer.len(), 150);
+		assert_eq!(layer.get_split_buffer_size(), 0);
+	}
+
+	/**
+	 * xorshift64, the harness has to be reproducible
+	 */
+	struct Rng(u64);
+
+	impl Rng {
+		fn below(&mut self, n: usize) -> usize {
+			self.0 ^= self.0 << 13;
+			self.0 ^= self.0 >> 7;
+			self.0 ^= self.0 << 17;
+			(self.0 % n as u64) as usize
+		}
+	}
+
+	/**
+	 * Drops, duplicates and shuffles packets. Reliable packets are never lost, the dropped ones arrive later as resends
+	 */
+	fn network(packets: Vec<EncapsulatedPacket>, rng: &mut Rng) -> Vec<EncapsulatedPacket> {
+		let mut received = vec![];
+		let mut resent = vec![];
+		for packet in packets {
+			match rng.below(10) {
+				0 if packet.reliability.is_reliable() => resent.push(packet),
+				0 => {},
+				1 => {
+					received.push(packet.clone());
+					received.push(packet);
+				},
+				_ => received.push(packet)
+			}
+		}
+		for i in (1..received.len()).rev() {
+			received.swap(i, rng.below(i + 1));
 		}
+		received.extend(resent);
+		received
+	}
+
+	fn id(packet: &EncapsulatedPacket) -> u32 {
+		u32::from_be_bytes(packet.buffer[..4].try_into().unwrap())
+	}
 
-		self.nack_queue.get_mut(sequence_number).map(| x | *x = None);
-		if self.ack_queue.len() - 1 < sequence_number {
-			let diff = sequence_number - self.ack_queue.len() - 1;
-			self.ack_queue.extend(repeat(None).take(diff));
+	fn deliver(packets: &[EncapsulatedPacket]) -> Vec<u32> {
+		let delivered: Arc<Mutex<Vec<u32>>> = Default::default();
+		let delivered_clone = delivered.clone();
+		let mut layer = ReceiveReliabilityLayer::new(move | pk | delivered_clone.lock().push(id(pk)), | _ | {});
+		for packet in packets {
+			layer.handle_encapsulated_packet(packet.clone());
 		}
-		self.ack_queue[sequence_number] = Some(sequence_number as u32);
+		let delivered = delivered.lock().clone();
+		delivered
+	}
 
-		if sequence_number == self.window_start {
-			//got a contiguous packet, shift the receive window
-			//this packet might complete a sequence of out-of-order packets, so we incrementally check the indexes
-			//to see how far to shift the window, and stop as soon as we either find a gap or have an empty window
-			while self.ack_queue.get(self.window_start).is_some() {
-				self.window_start += 1;
-				self.window_end += 1;
+	/**
+	 * Ids which are newer than every id before them
+	 */
+	fn newest(ids: impl Iterator<Item = u32>) -> Vec<u32> {
+		let mut newest: Vec<u32> = vec![];
+		for id in ids {
+			if newest.last().is_none_or(| last | id > *last) {
+				newest.push(id);
 			}
-		} else if sequence_number > self.window_start {
-			//we got a gap - a later packet arrived before earlier ones did
-			//we add the earlier ones to the NACK queue
-			//if the missing packets arrive before the end of tick, they'll be removed from the NACK queue
-			self.nack_queue = vec![None; sequence_number];
-			for i in self.window_start..sequence_number {
-				if self.ack_queue[i].is_none() {
-					self.nack_queue[i] = Some(i as u32);
+		}
+		newest
+	}
+
+	#[test]
+	fn delivery_order() {
+		let mut rng = Rng(0x2545f4914f6cdd1d);
+		let count: u32 = 500;
+		for reliability in (0..8).map(| x | PacketReliability::try_from(x).unwrap()) {
+			let packets = (0..count).map(| i | {
+				let mut packet = EncapsulatedPacket::default();
+				packet.reliability = reliability;
+				packet.order_channel = Some(3);
+				packet.buffer = i.to_be_bytes().to_vec();
+				packet
+			}).collect();
+			let received = network(send(packets, 128), &mut rng);
+			let delivered = deliver(&received);
+
+			let wire_reliability = reliability.to_wire();
+			if wire_reliability.is_ordered() {
+				assert_eq!(delivered, (0..count).collect::<Vec<u32>>(), "{:?}", reliability);
+			} else if wire_reliability.is_sequenced() {
+				assert_eq!(delivered, newest(received.iter().map(id)), "{:?}", reliability);
+				if wire_reliability.is_reliable() {
+					assert_eq!(delivered.last(), Some(&(count - 1)), "{:?}", reliability);
+				}
+			} else if wire_reliability.is_reliable() {
+				let mut first_seen = vec![];
+				for id in received.iter().map(id) {
+					if !first_seen.contains(&id) {
+						first_seen.push(id);
+					}
 				}
+				assert_eq!(first_seen.len(), count as usize);
+				assert_eq!(delivered, first_seen, "{:?}", reliability);
+			} else {
+				assert_eq!(delivered, received.iter().map(id).collect::<Vec<u32>>(), "{:?}", reliability);
 			}
-		} else {
-			panic!("received packet before window start");
 		}
+	}
+
+	#[test]
+	fn sequenced_waits_for_ordered() {
+		let reliabilities = [
+			PacketReliability::ReliableOrdered, //0
+			PacketReliability::UnreliableSequenced, //1, order index 1
+			PacketReliability::UnreliableSequenced, //2, order index 1
+			PacketReliability::ReliableOrdered, //3
+			PacketReliability::UnreliableSequenced //4, order index 2
+		];
+		let packets = reliabilities.iter().enumerate().map(| (i, reliability) | {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = *reliability;
+			packet.order_channel = Some(0);
+			packet.buffer = (i as u32).to_be_bytes().to_vec();
+			packet
+		}).collect();
+		let sent = send(packets, 128);
+		let received: Vec<EncapsulatedPacket> = [4, 2, 0, 1, 3].iter().map(| i | sent[*i].clone()).collect();
+		//2 has to wait for 0 and 4 for 3, 1 arrives after the newer 2 was delivered
+		assert_eq!(deliver(&received), vec![0, 2, 3, 4]);
+	}
+
+	#[test]
+	fn sequenced_buffer_limit() {
+		let delivered: Arc<Mutex<Vec<u32>>> = Default::default();
+		let delivered_clone = delivered.clone();
+		let mut layer = ReceiveReliabilityLayer::with_limits(move | pk | delivered_clone.lock().push(id(pk)), | _ | {}, 128, 4, 256);
+		let packet = | reliability, order_index: u32, sequence_index: u32 | {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = reliability;
+			packet.order_channel = Some(0);
+			packet.order_index = Some(order_index);
+			packet.sequence_index = Some(sequence_index);
+			packet.buffer = sequence_index.to_be_bytes().to_vec();
+			packet
+		};
 
-		for mut pk in &mut packet.packets {
-			self.handle_encapsulated_packet_route(&mut pk);
+		//duplicates aren't buffered twice
+		layer.handle_encapsulated_packet(packet(PacketReliability::UnreliableSequenced, 1, 0));
+		layer.handle_encapsulated_packet(packet(PacketReliability::UnreliableSequenced, 1, 0));
+		assert_eq!(layer.get_buffered_sequenced_count(0), 1);
+
+		//ordered packet 0 never arrives
+		for order_index in 1..100 {
+			for sequence_index in 0..1000 {
+				layer.handle_encapsulated_packet(packet(PacketReliability::UnreliableSequenced, order_index, sequence_index));
+			}
 		}
+		assert_eq!(layer.get_buffered_sequenced_count(0), 256);
+		assert_eq!(layer.receive_ordered_packets[0][1].sequenced.len(), ReceiveReliabilityLayer::MAX_SEQUENCED_PER_SLOT);
+		assert!(delivered.lock().is_empty());
+
+		let mut ordered = packet(PacketReliability::ReliableOrdered, 0, 0);
+		ordered.sequence_index = None;
+		layer.handle_encapsulated_packet(ordered);
+		assert_eq!(layer.get_buffered_sequenced_count(0), 256 - ReceiveReliabilityLayer::MAX_SEQUENCED_PER_SLOT);
+		assert_eq!(delivered.lock().len(), 1 + ReceiveReliabilityLayer::MAX_SEQUENCED_PER_SLOT);
 	}
 
-	pub fn update(&mut self) {
-		let diff = self.ack_queue.len() - self.window_start;
-		if diff > 0 {
-			//Move the receive window to account for packets we either received or are about to NACK
-			//we ignore any sequence numbers that we sent NACKs for, because we expect the client to resend them
-			//when it gets a NACK for it
+	#[test]
+	fn order_index_wraparound() {
+		let delivered: Arc<Mutex<Vec<u32>>> = Default::default();
+		let delivered_clone = delivered.clone();
+		let mut layer = ReceiveReliabilityLayer::new(move | pk | delivered_clone.lock().push(id(pk)), | _ | {});
+		layer.receive_ordered_index[0] = U24_MASK - 2;
+		for i in [3, 1, 0, 4, 2, 5] {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::ReliableOrdered;
+			packet.order_channel = Some(0);
+			packet.order_index = Some(u24_add(U24_MASK - 2, i));
+			packet.buffer = i.to_be_bytes().to_vec();
+			layer.handle_encapsulated_packet(packet);
+		}
+		assert_eq!(*delivered.lock(), vec![0, 1, 2, 3, 4, 5]);
+		assert_eq!(layer.receive_ordered_index[0], 3);
 
-			self.window_start += diff;
-			self.window_end += diff;
+		//sequenced packets waiting for an ordered one are delivered in serial order across the wrap
+		delivered.lock().clear();
+		for sequence_index in [U24_MASK, 1, U24_MASK - 1, 0] {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::UnreliableSequenced;
+			packet.order_channel = Some(0);
+			packet.order_index = Some(4);
+			packet.sequence_index = Some(sequence_index);
+			packet.buffer = sequence_index.to_be_bytes().to_vec();
+			layer.handle_encapsulated_packet(packet);
 		}
+		let mut packet = EncapsulatedPacket::default();
+		packet.reliability = PacketReliability::ReliableOrdered;
+		packet.order_channel = Some(0);
+		packet.order_index = Some(3);
+		packet.buffer = 3u32.to_be_bytes().to_vec();
+		layer.handle_encapsulated_packet(packet);
+		assert_eq!(*delivered.lock(), vec![3, U24_MASK - 1, U24_MASK, 0, 1]);
+	}
 
-		if !self.ack_queue.is_empty() {
-			let mut pk = ACK::default();
-			pk.packets = self.ack_queue.iter().filter_map(| x | x.to_owned()).collect();
-			(self.send_packet)(pk.into_dyn());
-			self.ack_queue.clear();
+	#[test]
+	fn session_wraparound() {
+		let start = U24_MASK - 100;
+		let wire: Arc<Mutex<Vec<Vec<u8>>>> = Default::default();
+		let wire_clone = wire.clone();
+		let mut sender = SendReliabilityLayer::with_resend_policy(
+			1000,
+			move | datagram | {
+				let mut buffer = vec![];
+				datagram.encode_packet(&mut buffer);
+				wire_clone.lock().push(buffer);
+			},
+			| _ | {},
+			| _ | {},
+			Duration::from_millis(20),
+			16
+		);
+		sender.skip_to_index(start);
+
+		let delivered: Arc<Mutex<Vec<u32>>> = Default::default();
+		let delivered_clone = delivered.clone();
+		let feedback: Arc<Mutex<Vec<Packet>>> = Default::default();
+		let feedback_clone = feedback.clone();
+		let mut receiver = ReceiveReliabilityLayer::new(
+			move | pk | delivered_clone.lock().push(id(pk)),
+			move | pk | feedback_clone.lock().push(pk)
+		);
+		receiver.skip_to_index(start);
+
+		let count: u32 = 300;
+		for i in 0..count {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::ReliableOrdered;
+			packet.order_channel = Some(0);
+			packet.buffer = i.to_be_bytes().to_vec();
+			packet.buffer.resize(400, 0);
+			sender.add_encapsulated_to_queue(packet, false);
 		}
 
-		if !self.nack_queue.is_empty() {
-			let mut pk = NACK::default();
-			pk.packets = self.nack_queue.iter().filter_map(| x | x.to_owned()).collect();
-			(self.send_packet)(pk.into_dyn());
-			self.nack_queue.clear();
+		let mut rng = Rng(0x9e3779b97f4a7c15);
+		let mut sequence_numbers = vec![];
+		for _ in 0..1000 {
+			sender.update();
+			let mut datagrams: Vec<Vec<u8>> = wire.lock().drain(..).collect();
+			for i in (1..datagrams.len()).rev() {
+				datagrams.swap(i, rng.below(i + 1));
+			}
+			for buffer in datagrams {
+				if rng.below(10) == 0 {
+					continue;
+				}
+				let mut datagram = Datagram::decode_packet(&mut buffer.as_slice()).unwrap();
+				sequence_numbers.push(datagram.sequence_number.unwrap());
+				receiver.on_datagram(&mut datagram);
+			}
+			receiver.update(false);
+			for packet in feedback.lock().drain(..) {
+				match packet.downcast::<ACK>() {
+					Ok(ack) => sender.on_ack(&ack),
+					Err(packet) => sender.on_nack(&packet.downcast::<NACK>().unwrap())
+				}
+			}
+			if delivered.lock().len() == count as usize {
+				break;
+			}
+			sleep(Duration::from_millis(1));
 		}
+
+		assert_eq!(*delivered.lock(), (0..count).collect::<Vec<u32>>());
+		assert!(sequence_numbers.contains(&start));
+		assert!(sequence_numbers.iter().any(| x | *x < start - U24_MASK / 2));
 	}
 
-	pub fn needs_update(&self) -> bool {
-		!self.ack_queue.is_empty() && !self.nack_queue.is_empty()
+	fn datagram(sequence_number: u32) -> Datagram {
+		Datagram {
+			sequence_number: Some(sequence_number),
+			..Default::default()
+		}
+	}
+
+	fn feedback(packets: &Mutex<Vec<Packet>>) -> (Vec<u32>, Vec<u32>) {
+		let mut acks = vec![];
+		let mut nacks = vec![];
+		for packet in packets.lock().drain(..) {
+			match packet.downcast::<ACK>() {
+				Ok(ack) => acks.extend(ack.acknowledge.packets),
+				Err(packet) => nacks.extend(packet.downcast::<NACK>().unwrap().acknowledge.packets)
+			}
+		}
+		(acks, nacks)
 	}
 
-}
\ No newline at end of file
+	#[test]
+	fn ack_batching() {
+		let sent: Arc<Mutex<Vec<Packet>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = ReceiveReliabilityLayer::new(| _ | {}, move | pk | sent_clone.lock().push(pk));
+		layer.set_ack_delay(Duration::from_millis(50));
+		layer.set_ack_batch_size(4);
+
+		layer.on_datagram(&mut datagram(0));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![], vec![]));
+		//piggybacked on outgoing datagrams
+		layer.update(true);
+		assert_eq!(feedback(&sent), (vec![0], vec![]));
+
+		for i in 1..4 {
+			layer.on_datagram(&mut datagram(i));
+		}
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![], vec![]));
+		layer.on_datagram(&mut datagram(4));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![1, 2, 3, 4], vec![]));
+
+		layer.on_datagram(&mut datagram(5));
+		sleep(Duration::from_millis(60));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![5], vec![]));
+		assert!(!layer.needs_update());
+	}
+
+	#[test]
+	fn nack_threshold() {
+		let sent: Arc<Mutex<Vec<Packet>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = ReceiveReliabilityLayer::new(| _ | {}, move | pk | sent_clone.lock().push(pk));
+		layer.set_ack_delay(Duration::ZERO);
+		layer.set_nack_delay(Duration::from_millis(50));
+
+		//reordered within the threshold
+		layer.on_datagram(&mut datagram(1));
+		layer.update(false);
+		layer.on_datagram(&mut datagram(0));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![1, 0], vec![]));
+
+		//lost
+		layer.on_datagram(&mut datagram(4));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![4], vec![]));
+		sleep(Duration::from_millis(60));
+		layer.on_datagram(&mut datagram(5));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![5], vec![2, 3]));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![], vec![]));
+
+		//the resends have new sequence numbers, a late original is ignored
+		layer.on_datagram(&mut datagram(2));
+		layer.update(false);
+		assert_eq!(feedback(&sent), (vec![], vec![]));
+		assert!(!layer.needs_update());
+	}
+}
@@ -1,16 +1,16 @@
 use crate::protocol::EncapsulatedPacket;
-use std::time::SystemTime;
+use std::time::Instant;
 
 pub struct ReliableCacheEntry {
 	pub packets: Vec<Box<EncapsulatedPacket>>,
-	pub timestamp: SystemTime
+	pub timestamp: Instant
 }
 
 impl ReliableCacheEntry {
 	pub fn new(packets: Vec<Box<EncapsulatedPacket>>) -> Self {
 		Self {
 			packets,
-			timestamp: SystemTime::now()
+			timestamp: Instant::now()
 		}
 	}
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+/**
+ * Smoothed round trip time and retransmission timeout as described by RFC 6298.
+ * Every timeout without a new sample in between doubles the retransmission timeout.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct RttEstimator {
+	smoothed_rtt: Option<Duration>,
+	rtt_variance: Duration,
+	backoff: u32,
+	max_retransmission_timeout: Duration
+}
+
+impl RttEstimator {
+
+	pub const INITIAL_RETRANSMISSION_TIMEOUT: Duration = Duration::from_secs(1);
+	pub const MIN_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(100);
+	/// Acknowledgements are sent once per tick
+	pub const CLOCK_GRANULARITY: Duration = Duration::from_millis(10);
+
+	const MAX_BACKOFF: u32 = 16;
+
+	pub fn new(max_retransmission_timeout: Duration) -> Self {
+		Self {
+			smoothed_rtt: None,
+			rtt_variance: Duration::ZERO,
+			backoff: 0,
+			max_retransmission_timeout
+		}
+	}
+
+	pub fn add_sample(&mut self, rtt: Duration) {
+		match self.smoothed_rtt {
+			None => {
+				self.smoothed_rtt = Some(rtt);
+				self.rtt_variance = rtt / 2;
+			},
+			Some(smoothed_rtt) => {
+				let deviation = if smoothed_rtt > rtt { smoothed_rtt - rtt } else { rtt - smoothed_rtt };
+				self.rtt_variance = (self.rtt_variance * 3 + deviation) / 4;
+				self.smoothed_rtt = Some((smoothed_rtt * 7 + rtt) / 8);
+			}
+		}
+		self.backoff = 0;
+	}
+
+	/**
+	 * Called when datagrams weren't acknowledged within the retransmission timeout
+	 */
+	pub fn on_timeout(&mut self) {
+		self.backoff = (self.backoff + 1).min(Self::MAX_BACKOFF);
+	}
+
+	/**
+	 * None until the first sample arrived
+	 */
+	pub fn get_smoothed_rtt(&self) -> Option<Duration> {
+		self.smoothed_rtt
+	}
+
+	pub fn get_rtt_variance(&self) -> Duration {
+		self.rtt_variance
+	}
+
+	pub fn get_retransmission_timeout(&self) -> Duration {
+		let timeout = match self.smoothed_rtt {
+			Some(smoothed_rtt) => smoothed_rtt + (self.rtt_variance * 4).max(Self::CLOCK_GRANULARITY),
+			None => Self::INITIAL_RETRANSMISSION_TIMEOUT
+		};
+		timeout.max(Self::MIN_RETRANSMISSION_TIMEOUT)
+			.saturating_mul(1 << self.backoff)
+			.min(self.max_retransmission_timeout)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use crate::generic::RttEstimator;
+
+	#[test]
+	fn retransmission_timeout() {
+		let mut rtt = RttEstimator::new(Duration::from_secs(8));
+		assert_eq!(rtt.get_retransmission_timeout(), Duration::from_secs(1));
+
+		rtt.add_sample(Duration::from_millis(100));
+		assert_eq!(rtt.get_smoothed_rtt(), Some(Duration::from_millis(100)));
+		assert_eq!(rtt.get_rtt_variance(), Duration::from_millis(50));
+		assert_eq!(rtt.get_retransmission_timeout(), Duration::from_millis(300));
+
+		rtt.add_sample(Duration::from_millis(180));
+		assert_eq!(rtt.get_smoothed_rtt(), Some(Duration::from_millis(110)));
+		assert_eq!(rtt.get_rtt_variance(), Duration::from_millis(57) + Duration::from_micros(500));
+
+		rtt.on_timeout();
+		rtt.on_timeout();
+		assert_eq!(rtt.get_retransmission_timeout(), Duration::from_millis(340 * 4));
+		for _ in 0..10 {
+			rtt.on_timeout();
+		}
+		assert_eq!(rtt.get_retransmission_timeout(), Duration::from_secs(8));
+
+		rtt.add_sample(Duration::from_millis(110));
+		assert!(rtt.get_retransmission_timeout() < Duration::from_millis(340));
+	}
+}
@@ -1,19 +1,22 @@
 use crate::protocol::{Datagram, EncapsulatedPacket, PacketReliability, SplitPacketInfo, ACK, NACK, PacketImpl};
-use crate::generic::ReliableCacheEntry;
+use crate::generic::{ReliableCacheEntry, CongestionController, SlidingWindow, RttEstimator, u24_add};
 use std::collections::{HashMap, VecDeque};
 use std::mem::replace;
-use std::time::{SystemTime, Duration};
+use std::time::{Instant, Duration};
+use log::debug;
 
 pub struct SendReliabilityLayer<'a> {
 	send_datagram_callback: Box<dyn Fn(&mut Datagram) -> () + Send + Sync + 'a>,
 
 	on_ack: Box<dyn Fn(u64) -> () + Send + Sync + 'a>,
 
+	on_loss: Box<dyn Fn(u64) -> () + Send + Sync + 'a>,
+
 	mtu_size: usize,
 
-	send_queue: Vec<Box<EncapsulatedPacket>>,
+	send_queue: VecDeque<Box<EncapsulatedPacket>>,
 
-	split_id: usize,
+	split_id: u16,
 
 	send_seq_number: u32,
 
@@ -22,24 +25,71 @@ pub struct SendReliabilityLayer<'a> {
 	send_ordered_index: [u32; PacketReliability::MAX_ORDER_CHANNELS],
 	send_sequenced_index: [u32; PacketReliability::MAX_ORDER_CHANNELS],
 
-	resend_queue: VecDeque<Datagram>,
+	resend_queue: VecDeque<Box<EncapsulatedPacket>>,
 
 	reliable_cache: HashMap<u32, ReliableCacheEntry>, //TODO replace hashmap
 
-	need_ack: HashMap<u64, HashMap<u32, u32>> //TODO replace hashmap
+	need_ack: HashMap<u64, usize>, //parts of every receipt which weren't acknowledged yet
+
+	receipts: HashMap<u32, Vec<u64>>, //receipts of the unreliable packets in every datagram
+
+	rtt: RttEstimator,
+
+	resend_limit: usize,
+
+	max_split_part_count: usize,
+
+	congestion_controller: Box<dyn CongestionController + 'a>,
+
+	pending: VecDeque<Datagram>, //waiting for the congestion window to open
+
+	in_flight: HashMap<u32, (usize, Instant)>, //bytes of every unacknowledged datagram
+
+	unacknowledged_bytes: usize,
+
+	sent_since_update: bool //ACKs of the receiving side are sent along with these datagrams
 }
 
 impl<'a> SendReliabilityLayer<'a> {
+
+	pub const DEFAULT_RESEND_TIMEOUT: Duration = Duration::from_secs(8);
+	pub const DEFAULT_RESEND_LIMIT: usize = 16;
+	pub const DEFAULT_MAX_SPLIT_PART_COUNT: usize = 128;
+
 	pub fn new(
 		mtu_size: usize,
 		send_datagram: impl Fn(&mut Datagram) -> () + Send + Sync + 'a,
-		on_ack: impl Fn(u64) -> () + Send + Sync + 'a
+		on_ack: impl Fn(u64) -> () + Send + Sync + 'a,
+		on_loss: impl Fn(u64) -> () + Send + Sync + 'a
+	) -> Self {
+		Self::with_resend_policy(
+			mtu_size,
+			send_datagram,
+			on_ack,
+			on_loss,
+			Self::DEFAULT_RESEND_TIMEOUT,
+			Self::DEFAULT_RESEND_LIMIT
+		)
+	}
+
+	/**
+	 * `resend_timeout` is the upper bound of the retransmission timeout,
+	 * `resend_limit` is the number of datagrams resent per update
+	 */
+	pub fn with_resend_policy(
+		mtu_size: usize,
+		send_datagram: impl Fn(&mut Datagram) -> () + Send + Sync + 'a,
+		on_ack: impl Fn(u64) -> () + Send + Sync + 'a,
+		on_loss: impl Fn(u64) -> () + Send + Sync + 'a,
+		resend_timeout: Duration,
+		resend_limit: usize
 	) -> Self {
 		Self {
 			send_datagram_callback: Box::new(send_datagram),
 			on_ack: Box::new(on_ack),
+			on_loss: Box::new(on_loss),
 			mtu_size,
-			send_queue: vec![],
+			send_queue: Default::default(),
 			split_id: 0,
 			send_seq_number: 0,
 			message_index: 0,
@@ -47,19 +97,79 @@ impl<'a> SendReliabilityLayer<'a> {
 			send_sequenced_index: Default::default(),
 			resend_queue: Default::default(),
 			reliable_cache: Default::default(),
-			need_ack: Default::default()
+			need_ack: Default::default(),
+			receipts: Default::default(),
+			rtt: RttEstimator::new(resend_timeout),
+			resend_limit,
+			max_split_part_count: Self::DEFAULT_MAX_SPLIT_PART_COUNT,
+			congestion_controller: Box::new(SlidingWindow::new(mtu_size)),
+			pending: Default::default(),
+			in_flight: Default::default(),
+			unacknowledged_bytes: 0,
+			sent_since_update: false
 		}
 	}
 
+	pub fn set_congestion_controller(&mut self, congestion_controller: Box<dyn CongestionController + 'a>) {
+		self.congestion_controller = congestion_controller;
+	}
+
+	/**
+	 * Packets are split into at most this many parts, it must not exceed the limit of the receiving peer
+	 */
+	pub fn set_max_split_part_count(&mut self, max_split_part_count: usize) {
+		self.max_split_part_count = max_split_part_count;
+	}
+
+	pub fn get_unacknowledged_bytes(&self) -> usize {
+		self.unacknowledged_bytes
+	}
+
+	pub fn get_rtt(&self) -> &RttEstimator {
+		&self.rtt
+	}
+
+	/**
+	 * Round trip time measured outside of the reliability layer, e.g. with connected pings
+	 */
+	pub fn add_rtt_sample(&mut self, rtt: Duration) {
+		self.rtt.add_sample(rtt);
+	}
+
+	/**
+	 * Starts the counters at `index`, so tests can cross the 24-bit wrap point
+	 */
+	#[cfg(test)]
+	pub(crate) fn skip_to_index(&mut self, index: u32) {
+		self.send_seq_number = index;
+		self.message_index = index;
+		self.send_ordered_index = [index; PacketReliability::MAX_ORDER_CHANNELS];
+	}
+
 	fn send_datagram(&mut self, mut datagram: Datagram) {
 		if let Some(sequence_number) = datagram.sequence_number {
 			self.reliable_cache.remove(&sequence_number);
 		}
 		datagram.sequence_number = Some(self.send_seq_number);
-		self.send_seq_number += 1;
+		self.send_seq_number = u24_add(self.send_seq_number, 1);
 		(self.send_datagram_callback)(&mut datagram);
+		self.sent_since_update = true;
 
 		let seq_number = datagram.sequence_number.unwrap();
+		let length = datagram.get_length();
+		self.in_flight.insert(seq_number, (length, Instant::now()));
+		self.unacknowledged_bytes += length;
+		self.congestion_controller.on_send(seq_number, length);
+
+		//reliable packets report their receipts through the reliable cache, they may be resent in another datagram
+		let receipts: Vec<u64> = datagram.packets.iter()
+			.filter(| x | !x.reliability.is_reliable())
+			.filter_map(| x | x.identifier_ack)
+			.collect();
+		if !receipts.is_empty() {
+			self.receipts.insert(seq_number, receipts);
+		}
+
 		let resendable: Vec<Box<EncapsulatedPacket>> = datagram.packets.into_iter().filter(| x | x.reliability.is_reliable()).collect();
 		if !resendable.is_empty() {
 			self.reliable_cache.insert(seq_number, ReliableCacheEntry::new(resendable));
@@ -69,16 +179,63 @@ impl<'a> SendReliabilityLayer<'a> {
 	pub fn send_queue(&mut self) {
 		if !self.send_queue.is_empty() {
 			let mut datagram = Datagram::default();
-			datagram.packets = replace(&mut self.send_queue, vec![]);
+			datagram.packets = std::mem::take(&mut self.send_queue).into();
+			self.pending.push_back(datagram);
+		}
+		self.flush();
+	}
+
+	fn can_send(&self, length: usize) -> bool {
+		//always allow one datagram in flight, a window smaller than a datagram would stall the connection
+		self.unacknowledged_bytes == 0 || length <= self.congestion_controller.get_transmission_bandwidth(self.unacknowledged_bytes)
+	}
+
+	/**
+	 * Sends pending datagrams as long as the congestion window allows it
+	 */
+	fn flush(&mut self) {
+		while let Some(datagram) = self.pending.front() {
+			if !self.can_send(datagram.get_length()) {
+				break;
+			}
+			let datagram = self.pending.pop_front().unwrap();
 			self.send_datagram(datagram);
 		}
 	}
 
-	fn add_to_queue(&mut self, pk: EncapsulatedPacket, immediate: bool) {
-		if let (Some(identifier_ack), Some(message_index)) = (pk.identifier_ack, pk.message_index) {
-			self.need_ack.get_mut(&identifier_ack).unwrap().insert(message_index, message_index);
+	/**
+	 * Returns when the datagram was sent, None if it was already acknowledged or considered lost
+	 */
+	fn remove_in_flight(&mut self, seq: u32) -> Option<Instant> {
+		let (length, timestamp) = self.in_flight.remove(&seq)?;
+		self.unacknowledged_bytes -= length;
+		Some(timestamp)
+	}
+
+	fn ack_receipt(&mut self, identifier_ack: u64) {
+		if let Some(parts) = self.need_ack.get_mut(&identifier_ack) {
+			*parts -= 1;
+			if *parts == 0 {
+				self.need_ack.remove(&identifier_ack);
+				(self.on_ack)(identifier_ack);
+			}
+		}
+	}
+
+	/**
+	 * A receipt is lost as soon as one of its parts is, the remaining parts are ignored
+	 */
+	fn lose_receipts(&mut self, seq: u32) {
+		if let Some(receipts) = self.receipts.remove(&seq) {
+			for identifier_ack in receipts {
+				if self.need_ack.remove(&identifier_ack).is_some() {
+					(self.on_loss)(identifier_ack);
+				}
+			}
 		}
+	}
 
+	fn add_to_queue(&mut self, pk: EncapsulatedPacket) {
 		let mut length = Datagram::HEADER_SIZE;
 
 		for queued in &self.send_queue {
@@ -89,130 +246,214 @@ impl<'a> SendReliabilityLayer<'a> {
 			self.send_queue()
 		}
 
-		if pk.identifier_ack.is_some() {
-			self.send_queue.push(Box::new(pk));
-			//pk.identifier_ack = None;
-		} else {
-			self.send_queue.push(Box::new(pk))
-		}
+		self.send_queue.push_back(Box::new(pk));
+	}
 
-		if immediate {
-			// Forces pending sends to go out now, rather than waiting to the next update interval
-			self.send_queue();
+	pub fn add_encapsulated_to_queue(&mut self, mut packet: EncapsulatedPacket, immediate: bool) {
+		packet.reliability = packet.reliability.to_wire();
+
+		//IP header size (20 bytes) + UDP header size (8 bytes) + RakNet weird (8 bytes) + datagram header size (4 bytes) + max encapsulated packet header size (20 bytes)
+		let max_size = self.mtu_size - 60;
+		let part_count = packet.buffer.len().div_ceil(max_size);
+		if part_count > self.max_split_part_count {
+			debug!("Dropped packet of {} bytes, it needs {} split parts but the limit is {}", packet.buffer.len(), part_count, self.max_split_part_count);
+			return;
 		}
-	}
 
-	pub fn add_encapsulated_to_queue(&mut self, mut packet: EncapsulatedPacket, _immediate: bool) {
-		if let Some(identifier_ack) = packet.identifier_ack {
-			self.need_ack.insert(identifier_ack, Default::default());
+		if packet.reliability.is_ordered() || packet.reliability.is_sequenced() {
+			let order_channel = *packet.order_channel.get_or_insert(0) as usize;
+			if order_channel >= PacketReliability::MAX_ORDER_CHANNELS {
+				debug!("Dropped packet on order channel {}, the limit is {}", order_channel, PacketReliability::MAX_ORDER_CHANNELS);
+				return;
+			}
 		}
 
 		if packet.reliability.is_ordered() {
 			let order_channel = packet.order_channel.unwrap() as usize;
 			packet.order_index = Some(self.send_ordered_index[order_channel]);
-			self.send_ordered_index[order_channel] += 1;
+			self.send_ordered_index[order_channel] = u24_add(self.send_ordered_index[order_channel], 1);
+			//the receiver discards sequenced packets older than an ordered one
+			self.send_sequenced_index[order_channel] = 0;
 		} else if packet.reliability.is_sequenced() {
 			let order_channel = packet.order_channel.unwrap() as usize;
 			packet.order_index = Some(self.send_ordered_index[order_channel]);
-			packet.sequence_index = Some(self.send_sequenced_index[order_channel])
+			packet.sequence_index = Some(self.send_sequenced_index[order_channel]);
+			self.send_sequenced_index[order_channel] = u24_add(self.send_sequenced_index[order_channel], 1);
 		}
 
-		//IP header size (20 bytes) + UDP header size (8 bytes) + RakNet weird (8 bytes) + datagram header size (4 bytes) + max encapsulated packet header size (20 bytes)
-		let max_size = self.mtu_size - 60;
-
-		if packet.buffer.len() > max_size {
-			let buffers: Vec<&[u8]> = packet.buffer.chunks(max_size).collect();
-			let buffer_count = buffers.len() as u32;
+		if let Some(identifier_ack) = packet.identifier_ack {
+			self.need_ack.insert(identifier_ack, part_count.max(1));
+		}
 
-			self.split_id += 1;
-			let split_id = (self.split_id % 65536) as u16;
-			let count = 0;
-			for buffer in buffers {
+		if part_count > 1 {
+			self.split_id = self.split_id.wrapping_add(1);
+			let split_id = self.split_id;
+			for (part_index, buffer) in packet.buffer.chunks(max_size).enumerate() {
 				let mut pk = EncapsulatedPacket::default();
-				pk.split_info = Some(SplitPacketInfo::new(split_id, count, buffer_count));
+				pk.split_info = Some(SplitPacketInfo::new(split_id, part_index as u32, part_count as u32));
 				pk.reliability = packet.reliability;
 				pk.buffer.extend(buffer);
 
+				//every part is acknowledged on its own, but all of them share the order and sequence indices
 				if pk.reliability.is_reliable() {
 					pk.message_index = Some(self.message_index);
-					self.message_index += 1;
+					self.message_index = u24_add(self.message_index, 1);
 				}
 
 				pk.sequence_index = packet.sequence_index;
 				pk.order_channel = packet.order_channel;
 				pk.order_index = packet.order_index;
+				pk.identifier_ack = packet.identifier_ack;
 
-				self.add_to_queue(pk, true);
+				self.add_to_queue(pk);
 			}
 		} else {
 			if packet.reliability.is_reliable() {
 				packet.message_index = Some(self.message_index);
-				self.message_index += 1;
+				self.message_index = u24_add(self.message_index, 1);
 			}
-			self.add_to_queue(packet, false);
+			self.add_to_queue(packet);
 		}
 
+		if immediate {
+			// Forces pending sends to go out now, rather than waiting to the next update interval
+			self.send_queue();
+		}
 	}
 
 	pub fn on_ack(&mut self, packet: &ACK) {
 		for seq in &packet.packets {
-			if let Some(reliable_cache) = self.reliable_cache.get(seq) {
-				for pk in &reliable_cache.packets {
-					if pk.identifier_ack.is_some() && pk.message_index.is_some() {
-						self.need_ack.get_mut(&pk.identifier_ack.unwrap()).unwrap().remove(&pk.message_index.unwrap());
-						if self.need_ack.get(&pk.identifier_ack.unwrap()).unwrap().is_empty() {
-							self.need_ack.remove(&pk.identifier_ack.unwrap());
-							(self.on_ack)(pk.identifier_ack.unwrap())
-						}
-					}
+			if let Some(timestamp) = self.remove_in_flight(*seq) {
+				//every resend gets a new sequence number, so the sample can't be ambiguous
+				self.rtt.add_sample(timestamp.elapsed());
+				let is_continuous_send = !self.pending.is_empty();
+				self.congestion_controller.on_ack(*seq, is_continuous_send);
+			}
+			if let Some(receipts) = self.receipts.remove(seq) {
+				for identifier_ack in receipts {
+					self.ack_receipt(identifier_ack);
+				}
+			}
+			if let Some(reliable_cache) = self.reliable_cache.remove(seq) {
+				for identifier_ack in reliable_cache.packets.iter().filter_map(| x | x.identifier_ack) {
+					self.ack_receipt(identifier_ack);
 				}
 			}
 		}
+		self.flush();
 	}
 
 	pub fn on_nack(&mut self, packet: &NACK) {
 		for seq in &packet.packets {
-			if let Some(_reliable_cache) = self.reliable_cache.get(seq) {
-				//TODO: group resends if the resulting datagram is below the MTU
-				let mut resend = Datagram::default();
-				resend.packets = self.reliable_cache.remove(&seq).unwrap().packets;
-				self.resend_queue.push_back(resend);
+			if self.remove_in_flight(*seq).is_some() {
+				let is_continuous_send = !self.pending.is_empty();
+				self.congestion_controller.on_nack(*seq, is_continuous_send);
+			}
+			self.lose_receipts(*seq);
+			if let Some(reliable_cache) = self.reliable_cache.remove(seq) {
+				self.resend_queue.extend(reliable_cache.packets);
 			}
 		}
 	}
 
-	pub fn needs_update(&self) -> bool {
-		!self.send_queue.is_empty() &&
-		!self.resend_queue.is_empty() &&
-		!self.reliable_cache.is_empty()
+	/**
+	 * Puts the packets of a resend datagram the congestion window didn't allow back in front of the resend queue
+	 */
+	fn requeue_resends(&mut self, datagram: Datagram) {
+		for packet in datagram.packets.into_iter().rev() {
+			self.resend_queue.push_front(packet);
+		}
 	}
 
-	pub fn update(&mut self) {
-		if !self.resend_queue.is_empty() {
-			let mut limit = 16;
-			while let Some(pk) = self.resend_queue.pop_front() {
-				self.send_datagram(pk);
+	/**
+	 * Repacks lost packets into as few datagrams as possible, the last one is filled up with queued packets.
+	 * Resends count against the congestion window like any other datagram, queued packets only join them if it allows.
+	 */
+	fn send_resends(&mut self) {
+		let max_length = self.mtu_size - 36;
+		let mut sent = 0;
+		let mut datagram = Datagram::default();
+		let mut length = Datagram::HEADER_SIZE;
+		while let Some(packet) = self.resend_queue.pop_front() {
+			let packet_length = packet.get_total_length();
+			if length + packet_length > max_length && !datagram.packets.is_empty() {
+				if !self.can_send(length) {
+					self.resend_queue.push_front(packet);
+					self.requeue_resends(datagram);
+					return;
+				}
+				self.send_datagram(replace(&mut datagram, Datagram::default()));
+				length = Datagram::HEADER_SIZE;
+				sent += 1;
+				if sent == self.resend_limit {
+					self.resend_queue.push_front(packet);
+					return;
+				}
+			}
+			length += packet_length;
+			datagram.packets.push(packet);
+		}
 
-				limit -= 1;
-				if limit <= 0 {
+		if !datagram.packets.is_empty() {
+			if !self.can_send(length) {
+				self.requeue_resends(datagram);
+				return;
+			}
+			while let Some(packet) = self.send_queue.front() {
+				let packet_length = packet.get_total_length();
+				if length + packet_length > max_length || !self.can_send(length + packet_length) {
 					break;
 				}
+				length += packet_length;
+				datagram.packets.push(self.send_queue.pop_front().unwrap());
 			}
+			self.send_datagram(datagram);
 		}
+	}
 
-		let keys: Vec<u32> = self.reliable_cache.keys().map(| x | *x).collect();
+	pub fn needs_update(&self) -> bool {
+		!self.send_queue.is_empty() ||
+		!self.pending.is_empty() ||
+		!self.resend_queue.is_empty() ||
+		!self.reliable_cache.is_empty()
+	}
+
+	/**
+	 * Returns whether datagrams were sent since the last update
+	 */
+	pub fn update(&mut self) -> bool {
+		if !self.resend_queue.is_empty() {
+			self.send_resends();
+		}
+
+		let now = Instant::now();
+		let retransmission_timeout = self.rtt.get_retransmission_timeout();
+		let lost: Vec<u32> = self.in_flight.iter()
+			.filter(| (_, (_, timestamp)) | now.duration_since(*timestamp) >= retransmission_timeout)
+			.map(| (seq, _) | *seq)
+			.collect();
+		if !lost.is_empty() {
+			self.rtt.on_timeout();
+		}
+		for seq in lost {
+			self.remove_in_flight(seq);
+			let is_continuous_send = !self.pending.is_empty();
+			self.congestion_controller.on_resend(seq, is_continuous_send);
+			self.lose_receipts(seq);
+		}
+
+		let keys: Vec<u32> = self.reliable_cache.iter()
+			.filter(| (_, entry) | now.duration_since(entry.timestamp) >= retransmission_timeout)
+			.map(| (seq, _) | *seq)
+			.collect();
 
 		for seq in keys {
-			if SystemTime::now().duration_since(self.reliable_cache[&seq].timestamp).unwrap() < Duration::from_secs(8) {
-				let mut resend = Datagram::default();
-				resend.packets = self.reliable_cache.remove(&seq).unwrap().packets;
-				self.resend_queue.push_back(resend);
-			} else {
-				break;
-			}
+			let reliable_cache = self.reliable_cache.remove(&seq).unwrap();
+			self.resend_queue.extend(reliable_cache.packets);
 		}
 
 		self.send_queue();
+		std::mem::take(&mut self.sent_since_update)
 	}
 
 	pub(crate) fn queue_connected_packet(
@@ -229,4 +470,275 @@ impl<'a> SendReliabilityLayer<'a> {
 
 		self.add_encapsulated_to_queue(encapsulated, immediate);
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::thread::sleep;
+	use std::time::Duration;
+	use parking_lot::Mutex;
+	use crate::generic::SendReliabilityLayer;
+	use crate::protocol::{EncapsulatedPacket, PacketReliability, ACK, NACK};
+
+	#[test]
+	fn congestion_window() {
+		let sent: Arc<Mutex<Vec<u32>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | sent_clone.lock().push(datagram.sequence_number.unwrap()),
+			| _ | {},
+			| _ | {}
+		);
+		for _ in 0..5 {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::Unreliable;
+			packet.buffer = vec![0xfe; 900];
+			layer.add_encapsulated_to_queue(packet, true);
+		}
+		layer.update();
+		//the initial window only fits a single datagram
+		assert_eq!(*sent.lock(), vec![0]);
+		assert!(layer.needs_update());
+
+		let mut ack = ACK::default();
+		ack.packets = vec![0];
+		layer.on_ack(&ack);
+		assert_eq!(*sent.lock(), vec![0, 1, 2]);
+		assert!(layer.get_rtt().get_smoothed_rtt().is_some());
+	}
+
+	#[test]
+	fn retransmission_timeout() {
+		let sent: Arc<Mutex<Vec<u32>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::with_resend_policy(
+			1000,
+			move | datagram | sent_clone.lock().push(datagram.sequence_number.unwrap()),
+			| _ | {},
+			| _ | {},
+			Duration::from_millis(20),
+			16
+		);
+		let mut packet = EncapsulatedPacket::default();
+		packet.reliability = PacketReliability::Reliable;
+		packet.buffer = vec![0xfe; 10];
+		layer.add_encapsulated_to_queue(packet, true);
+		layer.update();
+		layer.update();
+		assert_eq!(*sent.lock(), vec![0]);
+
+		sleep(Duration::from_millis(30));
+		layer.update();
+		layer.update();
+		assert_eq!(*sent.lock(), vec![0, 1]);
+	}
+
+	#[test]
+	fn coalesce_resends() {
+		let sent: Arc<Mutex<Vec<(u32, usize)>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | sent_clone.lock().push((datagram.sequence_number.unwrap(), datagram.packets.len())),
+			| _ | {},
+			| _ | {}
+		);
+		for _ in 0..3 {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::Reliable;
+			packet.buffer = vec![0xfe; 100];
+			layer.add_encapsulated_to_queue(packet, false);
+			layer.update();
+		}
+		assert_eq!(*sent.lock(), vec![(0, 1), (1, 1), (2, 1)]);
+
+		let mut packet = EncapsulatedPacket::default();
+		packet.reliability = PacketReliability::Unreliable;
+		packet.buffer = vec![0xfe; 100];
+		layer.add_encapsulated_to_queue(packet, false);
+
+		let mut nack = NACK::default();
+		nack.packets = vec![0, 1, 2];
+		layer.on_nack(&nack);
+		layer.update();
+		//the lost packets and the queued one share a single datagram
+		assert_eq!(*sent.lock(), vec![(0, 1), (1, 1), (2, 1), (3, 4)]);
+	}
+
+	#[test]
+	fn receipts() {
+		let acked: Arc<Mutex<Vec<u64>>> = Default::default();
+		let lost: Arc<Mutex<Vec<u64>>> = Default::default();
+		let acked_clone = acked.clone();
+		let lost_clone = lost.clone();
+		let mut layer = SendReliabilityLayer::with_resend_policy(
+			1000,
+			| _ | {},
+			move | identifier_ack | acked_clone.lock().push(identifier_ack),
+			move | identifier_ack | lost_clone.lock().push(identifier_ack),
+			Duration::from_millis(20),
+			16
+		);
+		let mut packet = EncapsulatedPacket::default();
+		packet.reliability = PacketReliability::ReliableOrderedWithAckReceipt;
+		packet.order_channel = Some(0);
+		packet.identifier_ack = Some(1);
+		packet.buffer = vec![0xfe; 2000];
+		layer.add_encapsulated_to_queue(packet, false);
+		layer.update();
+
+		//the receipt is reported once all split parts arrived
+		let mut ack = ACK::default();
+		for seq in 0..3 {
+			assert!(acked.lock().is_empty());
+			ack.packets = vec![seq];
+			layer.on_ack(&ack);
+		}
+		assert_eq!(*acked.lock(), vec![1]);
+
+		for identifier_ack in 2..4 {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::UnreliableWithAckReceipt;
+			packet.identifier_ack = Some(identifier_ack);
+			packet.buffer = vec![0xfe; 900];
+			layer.add_encapsulated_to_queue(packet, true);
+		}
+		let mut nack = NACK::default();
+		nack.packets = vec![3];
+		layer.on_nack(&nack);
+		layer.update();
+		assert_eq!(*lost.lock(), vec![2]);
+
+		sleep(Duration::from_millis(30));
+		layer.update();
+		assert_eq!(*lost.lock(), vec![2, 3]);
+		ack.packets = vec![4];
+		layer.on_ack(&ack);
+		assert_eq!(*acked.lock(), vec![1]);
+	}
+
+	#[test]
+	fn wire_reliability() {
+		let reliabilities: Arc<Mutex<Vec<u8>>> = Default::default();
+		let reliabilities_clone = reliabilities.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | reliabilities_clone.lock().extend(datagram.packets.iter().map(| x | x.reliability as u8)),
+			| _ | {},
+			| _ | {}
+		);
+		for reliability in [PacketReliability::UnreliableWithAckReceipt, PacketReliability::ReliableWithAckReceipt, PacketReliability::ReliableOrderedWithAckReceipt] {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = reliability;
+			packet.order_channel = Some(0);
+			packet.identifier_ack = Some(0);
+			packet.buffer = vec![0xfe; 10];
+			layer.add_encapsulated_to_queue(packet, false);
+		}
+		layer.update();
+		assert_eq!(*reliabilities.lock(), vec![PacketReliability::Unreliable as u8, PacketReliability::Reliable as u8, PacketReliability::ReliableOrdered as u8]);
+	}
+
+	#[test]
+	fn order_channel() {
+		let channels: Arc<Mutex<Vec<Option<u8>>>> = Default::default();
+		let channels_clone = channels.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | channels_clone.lock().extend(datagram.packets.iter().map(| x | x.order_channel)),
+			| _ | {},
+			| _ | {}
+		);
+		for (reliability, order_channel) in [
+			(PacketReliability::ReliableOrdered, None),
+			(PacketReliability::UnreliableSequenced, None),
+			(PacketReliability::ReliableOrdered, Some(PacketReliability::MAX_ORDER_CHANNELS as u8)),
+			(PacketReliability::ReliableSequenced, Some(u8::MAX))
+		] {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = reliability;
+			packet.order_channel = order_channel;
+			packet.buffer = vec![0xfe; 10];
+			layer.add_encapsulated_to_queue(packet, false);
+		}
+		layer.update();
+		//a missing channel defaults to 0, an invalid one is dropped
+		assert_eq!(*channels.lock(), vec![Some(0), Some(0)]);
+	}
+
+	#[test]
+	fn windowed_resends() {
+		let sent: Arc<Mutex<Vec<(u32, u32)>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | sent_clone.lock().push((datagram.sequence_number.unwrap(), datagram.packets[0].message_index.unwrap())),
+			| _ | {},
+			| _ | {}
+		);
+		let queue_packet = | layer: &mut SendReliabilityLayer | {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::Reliable;
+			packet.buffer = vec![0xfe; 900];
+			layer.add_encapsulated_to_queue(packet, true);
+		};
+		for _ in 0..4 {
+			queue_packet(&mut layer);
+		}
+		let mut ack = ACK::default();
+		ack.packets = vec![0];
+		layer.on_ack(&ack);
+		assert_eq!(*sent.lock(), vec![(0, 0), (1, 1), (2, 2)]);
+
+		let mut nack = NACK::default();
+		nack.packets = vec![1];
+		layer.on_nack(&nack);
+		//new data fills the window again before the resend goes out
+		queue_packet(&mut layer);
+		layer.update();
+		assert_eq!(*sent.lock(), vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+
+		ack.packets = vec![2];
+		layer.on_ack(&ack);
+		assert_eq!(*sent.lock(), vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 4)]);
+		//the loss stopped slow start, the window grows too slowly to make room for the resend yet
+		layer.update();
+		assert_eq!(*sent.lock(), vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 4)]);
+
+		ack.packets = vec![3];
+		layer.on_ack(&ack);
+		layer.update();
+		assert_eq!(*sent.lock(), vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 4), (5, 1)]);
+	}
+
+	#[test]
+	fn windowed_coalescing() {
+		let sent: Arc<Mutex<Vec<(u32, usize)>>> = Default::default();
+		let sent_clone = sent.clone();
+		let mut layer = SendReliabilityLayer::new(
+			1000,
+			move | datagram | sent_clone.lock().push((datagram.sequence_number.unwrap(), datagram.packets.len())),
+			| _ | {},
+			| _ | {}
+		);
+		for length in [400, 100] {
+			let mut packet = EncapsulatedPacket::default();
+			packet.reliability = PacketReliability::Reliable;
+			packet.buffer = vec![0xfe; length];
+			layer.add_encapsulated_to_queue(packet, true);
+		}
+		let mut nack = NACK::default();
+		nack.packets = vec![1];
+		layer.on_nack(&nack);
+
+		let mut packet = EncapsulatedPacket::default();
+		packet.reliability = PacketReliability::Unreliable;
+		packet.buffer = vec![0xfe; 500];
+		layer.add_encapsulated_to_queue(packet, false);
+		layer.update();
+		//the queued packet would overflow the window, so the resend goes out alone
+		assert_eq!(*sent.lock(), vec![(0, 1), (1, 1), (2, 1)]);
+		assert!(layer.needs_update());
+	}
+}
@@ -0,0 +1,240 @@
+use std::convert::TryInto;
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
+use chacha20poly1305::aead::{Aead, Payload};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use parking_lot::Mutex;
+use crate::generic::{DatagramCipher, KeyPair};
+use crate::protocol::Datagram;
+
+/**
+ * ChaCha20-Poly1305 encryption of a connection. The keys are derived from an X25519 exchange between the server's
+ * static key pair and an ephemeral key pair of the client, whose public key is the handshake challenge.
+ * Packets carry their nonce, a counter, so reordered packets can still be decrypted.
+ */
+pub struct SessionCipher {
+	send_cipher: ChaCha20Poly1305,
+
+	receive_cipher: ChaCha20Poly1305,
+
+	send_nonce: Mutex<u64>,
+
+	replay_window: Mutex<(u64, u64)> //highest received nonce + 1, bitmap of the nonces below it
+}
+
+impl SessionCipher {
+	const HEADER: u8 = Datagram::FLAG_VALID;
+	const HEADER_SIZE: usize = 1 + 8; //header (1) + nonce (8)
+	const TAG_SIZE: usize = 16;
+	const REPLAY_WINDOW_SIZE: u64 = 64;
+
+	/// Bytes added to every packet, the header, nonce and authentication tag
+	pub const OVERHEAD: usize = Self::HEADER_SIZE + Self::TAG_SIZE;
+
+	fn new(send_key: [u8; 32], receive_key: [u8; 32]) -> Self {
+		Self {
+			send_cipher: ChaCha20Poly1305::new(&send_key.into()),
+			receive_cipher: ChaCha20Poly1305::new(&receive_key.into()),
+			send_nonce: Mutex::new(0),
+			replay_window: Mutex::new((0, 0))
+		}
+	}
+
+	fn derive(shared_secret: &[u8; 32], client_public_key: &[u8; 32], server_public_key: &[u8; 32], label: &[u8]) -> [u8; 32] {
+		let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(shared_secret).unwrap();
+		mac.update(label);
+		mac.update(client_public_key);
+		mac.update(server_public_key);
+		mac.finalize().into_bytes().into()
+	}
+
+	/**
+	 * Returns the cipher and the answer to the challenge, which proves that the server owns the key pair.
+	 * Returns None if the challenge isn't a usable public key.
+	 */
+	pub fn server(key_pair: &KeyPair, challenge: &[u8; 32]) -> Option<(Self, [u8; 32])> {
+		let shared_secret = key_pair.diffie_hellman(challenge)?;
+		let derive = | label: &[u8] | Self::derive(&shared_secret, challenge, &key_pair.get_public_key(), label);
+		Some((Self::new(derive(b"server key"), derive(b"client key")), derive(b"answer")))
+	}
+
+	/**
+	 * Returns None if the answer doesn't prove that the server owns `server_public_key`
+	 */
+	pub fn client(ephemeral_key_pair: &KeyPair, server_public_key: &[u8; 32], answer: &[u8; 32]) -> Option<Self> {
+		let shared_secret = ephemeral_key_pair.diffie_hellman(server_public_key)?;
+		let derive = | label: &[u8] | Self::derive(&shared_secret, &ephemeral_key_pair.get_public_key(), server_public_key, label);
+		//constant time comparison
+		if derive(b"answer").iter().zip(answer).fold(0, | difference, (a, b) | difference | (a ^ b)) != 0 {
+			return None;
+		}
+		Some(Self::new(derive(b"client key"), derive(b"server key")))
+	}
+
+	fn nonce(counter: u64) -> Nonce {
+		let mut nonce = [0; 12];
+		nonce[4..].copy_from_slice(&counter.to_be_bytes());
+		nonce.into()
+	}
+
+	fn is_replayed(window: &(u64, u64), nonce: u64) -> bool {
+		let (next, bitmap) = *window;
+		if nonce >= next {
+			return false;
+		}
+		let age = next - 1 - nonce;
+		age >= Self::REPLAY_WINDOW_SIZE || bitmap & (1 << age) != 0
+	}
+}
+
+impl DatagramCipher for SessionCipher {
+	fn encrypt(&self, payload: &[u8]) -> Vec<u8> {
+		let counter = {
+			let mut send_nonce = self.send_nonce.lock();
+			*send_nonce += 1;
+			*send_nonce - 1
+		};
+		let ciphertext = self.send_cipher.encrypt(&Self::nonce(counter), Payload {
+			msg: payload,
+			aad: &[Self::HEADER]
+		}).unwrap();
+		let mut buffer = Vec::with_capacity(Self::HEADER_SIZE + ciphertext.len());
+		buffer.push(Self::HEADER);
+		buffer.extend_from_slice(&counter.to_be_bytes());
+		buffer.extend(ciphertext);
+		buffer
+	}
+
+	fn decrypt(&self, payload: &[u8]) -> Option<Vec<u8>> {
+		if payload.len() < Self::HEADER_SIZE + Self::TAG_SIZE || payload[0] != Self::HEADER {
+			return None;
+		}
+		let counter = u64::from_be_bytes(payload[1..Self::HEADER_SIZE].try_into().unwrap());
+		if Self::is_replayed(&self.replay_window.lock(), counter) {
+			return None;
+		}
+		let plaintext = self.receive_cipher.decrypt(&Self::nonce(counter), Payload {
+			msg: &payload[Self::HEADER_SIZE..],
+			aad: &[Self::HEADER]
+		}).ok()?;
+
+		let mut window = self.replay_window.lock();
+		if Self::is_replayed(&window, counter) {
+			return None;
+		}
+		let (next, bitmap) = &mut *window;
+		if counter >= *next {
+			let shift = counter - *next + 1;
+			*bitmap = if shift >= Self::REPLAY_WINDOW_SIZE { 0 } else { *bitmap << shift };
+			*bitmap |= 1;
+			*next = counter + 1;
+		} else {
+			*bitmap |= 1 << (*next - 1 - counter);
+		}
+		Some(plaintext)
+	}
+
+	fn overhead(&self) -> usize {
+		Self::OVERHEAD
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::generic::{KeyPair, SessionCipher, DatagramCipher};
+	use crate::protocol::{EncodePacket, OpenConnectionReply1, OpenConnectionRequest2, OpenConnectionReply2, RemoteSystemRequiresPubKey};
+	use crate::protocol::tests::{addresses, round_trip as packet_round_trip};
+
+	fn handshake(server_key_pair: &KeyPair) -> (SessionCipher, SessionCipher) {
+		let ephemeral_key_pair = KeyPair::generate();
+		let (server, answer) = SessionCipher::server(server_key_pair, &ephemeral_key_pair.get_public_key()).unwrap();
+		let client = SessionCipher::client(&ephemeral_key_pair, &server_key_pair.get_public_key(), &answer).unwrap();
+		(server, client)
+	}
+
+	#[test]
+	fn round_trip() {
+		let (server, client) = handshake(&KeyPair::generate());
+		let first = client.encrypt(b"first");
+		let second = client.encrypt(b"second");
+		assert_eq!(first.len(), b"first".len() + SessionCipher::OVERHEAD);
+		assert_ne!(&first[9..14], b"first");
+		//reordered
+		assert_eq!(server.decrypt(&second).unwrap(), b"second");
+		assert_eq!(server.decrypt(&first).unwrap(), b"first");
+		assert_eq!(client.decrypt(&server.encrypt(b"reply")).unwrap(), b"reply");
+		//each direction has its own key
+		assert!(client.decrypt(&client.encrypt(b"loop")).is_none());
+	}
+
+	#[test]
+	fn tampering_and_replay() {
+		let (server, client) = handshake(&KeyPair::generate());
+		let packet = client.encrypt(b"payload");
+		for i in 0..packet.len() {
+			let mut tampered = packet.clone();
+			tampered[i] ^= 1;
+			assert!(server.decrypt(&tampered).is_none());
+		}
+		assert!(server.decrypt(&packet).is_some());
+		assert!(server.decrypt(&packet).is_none());
+
+		let packets: Vec<Vec<u8>> = (0..100).map(| _ | client.encrypt(b"payload")).collect();
+		assert!(server.decrypt(&packets[99]).is_some());
+		//too old to tell whether it was replayed
+		assert!(server.decrypt(&packets[10]).is_none());
+		assert!(server.decrypt(&packets[50]).is_some());
+		assert!(server.decrypt(&packets[50]).is_none());
+
+		let (_, other) = handshake(&KeyPair::generate());
+		assert!(server.decrypt(&other.encrypt(b"payload")).is_none());
+	}
+
+	#[test]
+	fn wrong_server_key() {
+		let server_key_pair = KeyPair::generate();
+		let ephemeral_key_pair = KeyPair::generate();
+		let (_, answer) = SessionCipher::server(&server_key_pair, &ephemeral_key_pair.get_public_key()).unwrap();
+		assert!(SessionCipher::client(&ephemeral_key_pair, &KeyPair::generate().get_public_key(), &answer).is_none());
+		assert!(SessionCipher::client(&ephemeral_key_pair, &server_key_pair.get_public_key(), &[0; 32]).is_none());
+		//low order point
+		assert!(SessionCipher::server(&server_key_pair, &[0; 32]).is_none());
+	}
+
+	#[test]
+	fn key_pair() {
+		let key_pair = KeyPair::generate();
+		let restored = KeyPair::from_secret_key(key_pair.get_secret_key());
+		assert_eq!(restored.get_public_key(), key_pair.get_public_key());
+	}
+
+	#[test]
+	fn handshake_packets() {
+		let reply1 = packet_round_trip(&OpenConnectionReply1::create(42, Some(0xdeadbeef), Some([1; 32]), 1400));
+		assert_eq!(reply1.cookie, Some(0xdeadbeef));
+		assert_eq!(reply1.public_key, Some([1; 32]));
+		assert_eq!(reply1.mtu_size, 1400);
+
+		for address in addresses() {
+			let mut buffer = Vec::new();
+			OpenConnectionRequest2 {
+				offline_message: Default::default(),
+				cookie: Some(0xdeadbeef),
+				challenge: Some([2; 32]),
+				client_id: 7,
+				server_address: address,
+				mtu_size: 1400
+			}.encode_packet(&mut buffer);
+			let request2 = OpenConnectionRequest2::decode_with_cookie(&mut buffer.as_slice()).unwrap();
+			assert_eq!(request2.challenge, Some([2; 32]));
+			assert_eq!(request2.server_address, address);
+			assert_eq!(request2.client_id, 7);
+
+			let reply2 = packet_round_trip(&OpenConnectionReply2::create(42, address, 1400, Some([3; 32])));
+			assert_eq!(reply2.answer, Some([3; 32]));
+			assert_eq!(reply2.client_address, address);
+		}
+
+		assert_eq!(packet_round_trip(&RemoteSystemRequiresPubKey::create(42)).server_id, 42);
+	}
+}
@@ -0,0 +1,130 @@
+use crate::generic::{CongestionController, u24_add, u24_is_before};
+
+/**
+ * Port of RakNet's CCRakNetSlidingWindow.
+ * Starts with a window of one datagram, doubles it every round trip until the slow start threshold is reached
+ * and then grows by one datagram per round trip. Losses halve the threshold, timeouts also reset the window.
+ */
+pub struct SlidingWindow {
+	mtu_size: usize,
+	congestion_window: usize,
+	slow_start_threshold: usize, //0 = not known yet
+	next_congestion_control_block: u32,
+	next_sequence_number: u32,
+	backoff_this_block: bool
+}
+
+impl SlidingWindow {
+	pub fn new(mtu_size: usize) -> Self {
+		Self {
+			mtu_size,
+			congestion_window: mtu_size,
+			slow_start_threshold: 0,
+			next_congestion_control_block: 0,
+			next_sequence_number: 0,
+			backoff_this_block: false
+		}
+	}
+
+	pub fn get_congestion_window(&self) -> usize {
+		self.congestion_window
+	}
+
+	pub fn is_in_slow_start(&self) -> bool {
+		self.slow_start_threshold == 0 || self.congestion_window <= self.slow_start_threshold
+	}
+
+	fn start_congestion_control_block(&mut self) {
+		self.backoff_this_block = false;
+		self.next_congestion_control_block = self.next_sequence_number;
+	}
+}
+
+impl CongestionController for SlidingWindow {
+	fn get_transmission_bandwidth(&self, unacknowledged_bytes: usize) -> usize {
+		self.congestion_window.saturating_sub(unacknowledged_bytes)
+	}
+
+	fn on_send(&mut self, sequence_number: u32, _bytes: usize) {
+		self.next_sequence_number = u24_add(sequence_number, 1);
+	}
+
+	fn on_ack(&mut self, sequence_number: u32, is_continuous_send: bool) {
+		if !is_continuous_send {
+			self.backoff_this_block = false;
+			return;
+		}
+
+		let is_new_congestion_control_block = !u24_is_before(sequence_number, self.next_congestion_control_block);
+		if is_new_congestion_control_block {
+			self.start_congestion_control_block();
+		}
+
+		if self.is_in_slow_start() {
+			self.congestion_window += self.mtu_size;
+			if self.slow_start_threshold != 0 && self.congestion_window > self.slow_start_threshold {
+				self.congestion_window = self.slow_start_threshold + self.mtu_size * self.mtu_size / self.congestion_window;
+			}
+		} else if is_new_congestion_control_block {
+			self.congestion_window += self.mtu_size * self.mtu_size / self.congestion_window;
+		}
+	}
+
+	fn on_nack(&mut self, _sequence_number: u32, is_continuous_send: bool) {
+		if is_continuous_send && !self.backoff_this_block {
+			self.slow_start_threshold = (self.congestion_window / 2).max(self.mtu_size);
+			self.backoff_this_block = true;
+		}
+	}
+
+	fn on_resend(&mut self, _sequence_number: u32, is_continuous_send: bool) {
+		if is_continuous_send && !self.backoff_this_block && self.congestion_window > self.mtu_size * 2 {
+			self.slow_start_threshold = (self.congestion_window / 2).max(self.mtu_size);
+			self.congestion_window = self.mtu_size;
+			self.next_congestion_control_block = self.next_sequence_number;
+			self.backoff_this_block = true;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::generic::{SlidingWindow, CongestionController};
+
+	#[test]
+	fn window() {
+		let mut window = SlidingWindow::new(1000);
+		assert_eq!(window.get_transmission_bandwidth(0), 1000);
+		assert_eq!(window.get_transmission_bandwidth(1000), 0);
+
+		//slow start grows by one datagram per ack
+		for seq in 0..4 {
+			window.on_send(seq, 1000);
+		}
+		for seq in 0..4 {
+			window.on_ack(seq, true);
+		}
+		assert_eq!(window.get_congestion_window(), 5000);
+
+		//losses halve the threshold but keep the window
+		window.on_nack(4, true);
+		assert!(!window.is_in_slow_start());
+		assert_eq!(window.get_congestion_window(), 5000);
+
+		//the window only grows once per block in congestion avoidance
+		window.on_send(4, 1000);
+		window.on_ack(4, true);
+		window.on_ack(4, true);
+		assert_eq!(window.get_congestion_window(), 5200);
+
+		//timeouts reset the window
+		window.on_send(5, 1000);
+		window.on_resend(5, true);
+		assert_eq!(window.get_congestion_window(), 1000);
+		assert!(window.is_in_slow_start());
+
+		//an idle sender doesn't grow the window
+		window.on_ack(6, false);
+		assert_eq!(window.get_congestion_window(), 1000);
+	}
+}
@@ -0,0 +1,161 @@
+use crate::protocol::EncapsulatedPacket;
+use std::time::Instant;
+
+/**
+ * Reassembles the parts of a split packet in a single buffer. Every part except the last one has the same size,
+ * so a part is copied to its final position as soon as it arrives.
+ */
+pub struct SplitPacket {
+	header: Option<EncapsulatedPacket>, //first received part, without its payload
+
+	buffer: Vec<u8>, //all parts except the last one, allocated when the part size is known
+
+	part_size: Option<usize>,
+
+	last_part: Option<Vec<u8>>,
+
+	received: Vec<bool>,
+
+	received_count: usize,
+
+	pub timestamp: Instant
+}
+
+impl SplitPacket {
+	pub fn new(part_count: usize) -> Self {
+		Self {
+			header: None,
+			buffer: vec![],
+			part_size: None,
+			last_part: None,
+			received: vec![false; part_count],
+			received_count: 0,
+			timestamp: Instant::now()
+		}
+	}
+
+	pub fn get_part_count(&self) -> usize {
+		self.received.len()
+	}
+
+	/**
+	 * Bytes allocated for the parts and their bookkeeping
+	 */
+	pub fn get_size(&self) -> usize {
+		Self::get_bookkeeping_size(self.get_part_count()) + self.buffer.len() + self.last_part.as_ref().map_or(0, Vec::len)
+	}
+
+	fn get_bookkeeping_size(part_count: usize) -> usize {
+		part_count * size_of::<bool>()
+	}
+
+	fn growth(part_count: usize, has_part_size: bool, part_index: usize, length: usize) -> usize {
+		if part_index + 1 == part_count {
+			length
+		} else if !has_part_size {
+			length * (part_count - 1)
+		} else {
+			0
+		}
+	}
+
+	/**
+	 * Bytes which would be allocated when a part of the given length is added
+	 */
+	pub fn get_growth(&self, part_index: usize, length: usize) -> usize {
+		Self::growth(self.get_part_count(), self.part_size.is_some(), part_index, length)
+	}
+
+	/**
+	 * Bytes which a new split packet would allocate for its first part, including the bookkeeping of all parts
+	 */
+	pub fn get_initial_size(part_count: usize, part_index: usize, length: usize) -> usize {
+		Self::get_bookkeeping_size(part_count) + Self::growth(part_count, false, part_index, length)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.received_count == 0
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.received_count == self.get_part_count()
+	}
+
+	/**
+	 * Returns false if the part was already received or its size doesn't match the other parts
+	 */
+	pub fn add_part(&mut self, part_index: usize, mut packet: EncapsulatedPacket) -> bool {
+		if part_index >= self.get_part_count() || self.received[part_index] {
+			return false;
+		}
+		let length = packet.buffer.len();
+		if part_index + 1 == self.get_part_count() {
+			if self.part_size.is_some_and(| part_size | length > part_size) {
+				return false;
+			}
+			self.last_part = Some(std::mem::take(&mut packet.buffer));
+		} else {
+			match self.part_size {
+				Some(part_size) if part_size != length => return false,
+				Some(_) => {},
+				None => {
+					if self.last_part.as_ref().is_some_and(| last_part | last_part.len() > length) {
+						return false;
+					}
+					self.part_size = Some(length);
+					self.buffer = vec![0; length * (self.get_part_count() - 1)];
+				}
+			}
+			let offset = part_index * length;
+			self.buffer[offset..offset + length].copy_from_slice(&packet.buffer);
+			packet.buffer.clear();
+		}
+
+		if self.header.is_none() {
+			packet.split_info = None;
+			self.header = Some(packet);
+		}
+		self.received[part_index] = true;
+		self.received_count += 1;
+		true
+	}
+
+	/**
+	 * Must only be called once the split packet is complete
+	 */
+	pub fn into_packet(self) -> EncapsulatedPacket {
+		let mut packet = self.header.unwrap();
+		packet.buffer = self.buffer;
+		packet.buffer.extend(self.last_part.unwrap());
+		packet
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::generic::SplitPacket;
+	use crate::protocol::EncapsulatedPacket;
+
+	fn part(buffer: &[u8]) -> EncapsulatedPacket {
+		let mut packet = EncapsulatedPacket::default();
+		packet.buffer = buffer.to_vec();
+		packet
+	}
+
+	#[test]
+	fn reassemble() {
+		assert_eq!(SplitPacket::get_initial_size(3, 2, 1), 4);
+		let mut split = SplitPacket::new(3);
+		assert_eq!(split.get_growth(2, 1), 1);
+		assert!(split.add_part(2, part(&[5])));
+		assert!(!split.add_part(2, part(&[5])));
+		assert_eq!(split.get_growth(1, 2), 4);
+		assert!(split.add_part(1, part(&[3, 4])));
+		assert_eq!(split.get_size(), 8);
+		assert!(!split.add_part(0, part(&[1, 2, 3])));
+		assert!(!split.is_complete());
+		assert!(split.add_part(0, part(&[1, 2])));
+		assert!(split.is_complete());
+		assert_eq!(split.into_packet().buffer, vec![1, 2, 3, 4, 5]);
+	}
+}
@@ -0,0 +1,41 @@
+/**
+ * Serial number arithmetic (RFC 1982) for the indices written as 24-bit triads
+ */
+pub const U24_MODULUS: u32 = 1 << 24;
+pub const U24_MASK: u32 = U24_MODULUS - 1;
+
+const U24_HALF: u32 = U24_MODULUS / 2;
+
+pub fn u24_add(index: u32, n: u32) -> u32 {
+	index.wrapping_add(n) & U24_MASK
+}
+
+/**
+ * Steps from `from` forward to `to`
+ */
+pub fn u24_distance(from: u32, to: u32) -> u32 {
+	to.wrapping_sub(from) & U24_MASK
+}
+
+/**
+ * Whether `a` comes before `b`, assuming they are less than half of the index space apart
+ */
+pub fn u24_is_before(a: u32, b: u32) -> bool {
+	a != b && u24_distance(a, b) < U24_HALF
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::generic::{u24_add, u24_distance, u24_is_before, U24_MASK};
+
+	#[test]
+	fn wraparound() {
+		assert_eq!(u24_add(U24_MASK, 1), 0);
+		assert_eq!(u24_add(U24_MASK - 1, 5), 3);
+		assert_eq!(u24_distance(U24_MASK - 1, 3), 5);
+		assert!(u24_is_before(U24_MASK, 0));
+		assert!(!u24_is_before(0, U24_MASK));
+		assert!(u24_is_before(3, 4));
+		assert!(!u24_is_before(4, 4));
+	}
+}
@@ -1,5 +1,5 @@
 use bytes::{BufMut, Buf};
-use crate::protocol::{AcknowledgePacket, MessageIdentifiers, EncodeBody, DecodeBody, CommonPacket, MessageIdentifierHeader};
+use crate::protocol::{AcknowledgePacket, MessageIdentifiers, EncodeBody, DecodeBody, CommonPacket, DecodeError, MessageIdentifierHeader};
 
 
 #[derive(Default, Debug, Deref, DerefMut)]
@@ -18,10 +18,10 @@ impl EncodeBody for ACK {
 }
 
 impl DecodeBody for ACK {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			acknowledge: AcknowledgePacket::decode_body(serializer)
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self {
+			acknowledge: AcknowledgePacket::decode_body(serializer)?
+		})
 	}
 }
 
@@ -1,5 +1,4 @@
-use bytes_addition::{PutTriad, GetTriad};
-use crate::protocol::{EncodeBody, DecodeBody};
+use crate::protocol::{EncodeBody, DecodeBody, DecodeError, Require, AcknowledgeRecord};
 use bytes::{Buf, BufMut};
 
 #[derive(Default, Debug)]
@@ -7,88 +6,172 @@ pub struct AcknowledgePacket {
 	pub packets: Vec<u32>
 }
 
-const RECORD_TYPE_RANGE: u8 = 0;
-const RECORD_TYPE_SINGLE: u8 = 1;
+impl AcknowledgePacket {
+	/// Decoding fails if the records acknowledge more sequence numbers, a single range could otherwise cover 16M of them
+	pub const MAX_SEQUENCE_NUMBERS: usize = 8192;
 
-impl EncodeBody for AcknowledgePacket {
-	fn encode_body(&self, serializer: &mut dyn BufMut) {
-		serializer.put_u8(10);
-		let mut payload: Vec<u8> = Vec::new();
-		let count = self.packets.len();
+	const HEADER_SIZE: usize = 1 + 2; //message identifier (1) + record count (2)
+
+	/**
+	 * Groups the sequence numbers into packets which encode to at most `max_size` bytes each and acknowledge at most
+	 * `MAX_SEQUENCE_NUMBERS` each, longer ranges are split between packets
+	 */
+	pub fn split(sequence_numbers: &[u32], max_size: usize) -> Vec<Self> {
+		let mut packets = Vec::new();
+		let mut packet = Self::default();
+		let mut length = Self::HEADER_SIZE;
 		let mut records = 0;
-		if count > 0 {
-			let mut packets = self.packets.clone();
-			packets.sort_unstable();
-
-			let mut pointer = 1;
-			let mut start = packets[0];
-			let mut last = packets[0];
-
-			while pointer < count {
-				let current = packets[pointer];
-				pointer += 1;
-				let diff = current - last;
-				if diff == 1 {
-					last = current;
-				} else if diff > 1 {
-					if start == last {
-						payload.put_u8(RECORD_TYPE_SINGLE);
-						payload.put_u24_le(start);
-						start = current;
-						last = current;
-					} else {
-						payload.put_u8(RECORD_TYPE_RANGE);
-						payload.put_u24_le(start);
-						payload.put_u24_le(last);
-						start = current;
-						last = current;
-					}
-					records += 1;
+		for record in AcknowledgeRecord::from_sequence_numbers(sequence_numbers) {
+			let (mut start, end) = record.get_sequence_numbers().into_inner();
+			loop {
+				let available = Self::MAX_SEQUENCE_NUMBERS - packet.packets.len();
+				let part = AcknowledgeRecord::new(start, end.min(start + available.saturating_sub(1) as u32));
+				if (length + part.get_length() > max_size || records == u16::MAX || available == 0) && records > 0 {
+					packets.push(std::mem::take(&mut packet));
+					length = Self::HEADER_SIZE;
+					records = 0;
+					continue;
 				}
+				length += part.get_length();
+				records += 1;
+				packet.packets.extend(part.get_sequence_numbers());
+
+				let last = *part.get_sequence_numbers().end();
+				if last == end {
+					break;
+				}
+				start = last + 1;
 			}
-			if start == last {
-				payload.put_u8(RECORD_TYPE_SINGLE);
-				payload.put_u24_le(start);
-			} else {
-				payload.put_u8(RECORD_TYPE_RANGE);
-				payload.put_u24_le(start);
-				payload.put_u24_le(last);
-			}
-			records += 1;
 		}
-		serializer.put_u8(records);
-		serializer.put_slice(payload.as_slice());
+		if records > 0 {
+			packets.push(packet);
+		}
+		packets
+	}
+}
+
+impl EncodeBody for AcknowledgePacket {
+	fn encode_body(&self, serializer: &mut dyn BufMut) {
+		let records = AcknowledgeRecord::from_sequence_numbers(&self.packets);
+		serializer.put_u16(records.len() as u16);
+		for record in records {
+			record.encode_body(serializer);
+		}
 	}
 }
 
 impl DecodeBody for AcknowledgePacket {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(2)?;
 		let count = serializer.get_u16();
 		let mut packets = Vec::new();
-		{
-			let mut i = 0;
-			while i < count && serializer.has_remaining() {
-				if serializer.get_u8() == RECORD_TYPE_RANGE {
-					let start = serializer.get_u24_le();
-					let end = {
-						let _end = serializer.get_u24_le();
-						if _end - start > 512 {
-							start + 512
-						} else {
-							_end
-						}
-					};
-					for c in start..=end {
-						packets.push(c);
-					}
-				} else {
-					packets.push(serializer.get_u24_le());
-				}
-				i += 1;
+		for _ in 0..count {
+			let record = AcknowledgeRecord::decode_body(serializer)?;
+			if packets.len() + record.get_count() > Self::MAX_SEQUENCE_NUMBERS {
+				return Err(DecodeError::TooManyAcknowledgements);
 			}
+			packets.extend(record.get_sequence_numbers());
 		}
-		Self {
+		Ok(Self {
 			packets
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use proptest::prelude::*;
+	use crate::protocol::{AcknowledgePacket, DecodeBody, DecodeError, EncodeBody, EncodePacket, ACK};
+
+	fn encode(sequence_numbers: &[u32]) -> Vec<u8> {
+		let mut buffer = vec![];
+		AcknowledgePacket { packets: sequence_numbers.to_vec() }.encode_body(&mut buffer);
+		buffer
+	}
+
+	fn sequence_numbers() -> impl Strategy<Value = Vec<u32>> {
+		prop::collection::vec((0u32..0xffff00, 1u32..40), 0..64)
+			.prop_map(| ranges | ranges.into_iter().flat_map(| (start, length) | start..start + length).collect())
+	}
+
+	/**
+	 * Ranges long enough to exceed `MAX_SEQUENCE_NUMBERS`
+	 */
+	fn long_sequence_numbers() -> impl Strategy<Value = Vec<u32>> {
+		prop::collection::vec((0u32..0xff0000, 1u32..6000), 0..8)
+			.prop_map(| ranges | ranges.into_iter().flat_map(| (start, length) | start..start + length).collect())
+	}
+
+	proptest! {
+		#[test]
+		fn round_trip(sequence_numbers in sequence_numbers()) {
+			let mut expected = sequence_numbers.clone();
+			expected.sort_unstable();
+			expected.dedup();
+			let decoded = AcknowledgePacket::decode_body(&mut encode(&sequence_numbers).as_slice()).unwrap();
+			prop_assert_eq!(decoded.packets, expected);
+		}
+
+		#[test]
+		fn split(sequence_numbers in prop_oneof![sequence_numbers(), long_sequence_numbers()], max_size in 10usize..200) {
+			let mut expected = sequence_numbers.clone();
+			expected.sort_unstable();
+			expected.dedup();
+			let mut joined = vec![];
+			for acknowledge in AcknowledgePacket::split(&sequence_numbers, max_size) {
+				prop_assert!(acknowledge.packets.len() <= AcknowledgePacket::MAX_SEQUENCE_NUMBERS);
+				let mut buffer = vec![];
+				ACK { acknowledge }.encode_packet(&mut buffer);
+				prop_assert!(buffer.len() <= max_size);
+				joined.extend(AcknowledgePacket::decode_body(&mut &buffer[1..]).unwrap().packets);
+			}
+			prop_assert_eq!(joined, expected);
+		}
+
+		#[test]
+		fn decode_arbitrary(buffer in prop::collection::vec(any::<u8>(), 0..256)) {
+			if let Ok(packet) = AcknowledgePacket::decode_body(&mut buffer.as_slice()) {
+				prop_assert!(packet.packets.len() <= AcknowledgePacket::MAX_SEQUENCE_NUMBERS);
+			}
+		}
+	}
+
+	#[test]
+	fn raknet_encoding() {
+		//record count (big endian), then per record: 1 for a single sequence number, 0 for a range, and the little endian triads
+		assert_eq!(encode(&[]), [0, 0]);
+		assert_eq!(encode(&[5]), [0, 1, 1, 5, 0, 0]);
+		assert_eq!(encode(&[3, 1, 2]), [0, 1, 0, 1, 0, 0, 3, 0, 0]);
+		assert_eq!(encode(&[0x123456, 4, 0, 3, 2, 2]), [
+			0, 3,
+			1, 0, 0, 0,
+			0, 2, 0, 0, 4, 0, 0,
+			1, 0x56, 0x34, 0x12
+		]);
+	}
+
+	#[test]
+	fn split_long_ranges() {
+		let mut sequence_numbers: Vec<u32> = (0..20000).collect();
+		sequence_numbers.push(20001);
+		let packets = AcknowledgePacket::split(&sequence_numbers, 1400);
+		assert_eq!(packets.iter().map(| x | x.packets.len()).collect::<Vec<_>>(), vec![8192, 8192, 3617]);
+		let mut joined = vec![];
+		for acknowledge in packets {
+			let mut buffer = vec![];
+			ACK { acknowledge }.encode_packet(&mut buffer);
+			joined.extend(AcknowledgePacket::decode_body(&mut &buffer[1..]).unwrap().packets);
 		}
+		assert_eq!(joined, sequence_numbers);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn hostile_ranges() {
+		//a single range covering every sequence number
+		let buffer = [0, 1, 0, 0, 0, 0, 0xff, 0xff, 0xff];
+		assert_eq!(AcknowledgePacket::decode_body(&mut &buffer[..]).unwrap_err(), DecodeError::TooManyAcknowledgements);
+
+		let buffer = [0, 1, 0, 5, 0, 0, 4, 0, 0];
+		assert_eq!(AcknowledgePacket::decode_body(&mut &buffer[..]).unwrap_err(), DecodeError::InvalidAcknowledgeRange { start: 5, end: 4 });
+	}
+}
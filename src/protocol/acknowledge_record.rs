@@ -0,0 +1,116 @@
+use bytes_addition::{PutTriad, GetTriad};
+use crate::protocol::{EncodeBody, DecodeBody, DecodeError, Require};
+use bytes::{Buf, BufMut};
+use std::ops::RangeInclusive;
+
+/**
+ * Sequence numbers acknowledged by an ACK or NACK, consecutive ones are merged into a range
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AcknowledgeRecord {
+	Single(u32),
+	Range {
+		start: u32,
+		end: u32
+	}
+}
+
+impl AcknowledgeRecord {
+	const TYPE_RANGE: u8 = 0;
+	const TYPE_SINGLE: u8 = 1;
+
+	/**
+	 * Sorts the sequence numbers and merges them into as few records as possible, duplicates are removed
+	 */
+	pub fn from_sequence_numbers(sequence_numbers: &[u32]) -> Vec<Self> {
+		let mut sequence_numbers = sequence_numbers.to_vec();
+		sequence_numbers.sort_unstable();
+		sequence_numbers.dedup();
+
+		let mut records = Vec::new();
+		let mut iter = sequence_numbers.into_iter();
+		let mut start = match iter.next() {
+			Some(start) => start,
+			None => return records
+		};
+		let mut end = start;
+		for sequence_number in iter {
+			if sequence_number - end != 1 {
+				records.push(Self::new(start, end));
+				start = sequence_number;
+			}
+			end = sequence_number;
+		}
+		records.push(Self::new(start, end));
+		records
+	}
+
+	pub fn new(start: u32, end: u32) -> Self {
+		if start == end {
+			AcknowledgeRecord::Single(start)
+		} else {
+			AcknowledgeRecord::Range {
+				start,
+				end
+			}
+		}
+	}
+
+	pub fn get_sequence_numbers(&self) -> RangeInclusive<u32> {
+		match *self {
+			AcknowledgeRecord::Single(sequence_number) => sequence_number..=sequence_number,
+			AcknowledgeRecord::Range { start, end } => start..=end
+		}
+	}
+
+	pub fn get_count(&self) -> usize {
+		match *self {
+			AcknowledgeRecord::Single(_) => 1,
+			AcknowledgeRecord::Range { start, end } => (end - start) as usize + 1
+		}
+	}
+
+	pub fn get_length(&self) -> usize {
+		match self {
+			AcknowledgeRecord::Single(_) => 1 + 3,
+			AcknowledgeRecord::Range { .. } => 1 + 3 + 3
+		}
+	}
+}
+
+impl EncodeBody for AcknowledgeRecord {
+	fn encode_body(&self, serializer: &mut dyn BufMut) {
+		match *self {
+			AcknowledgeRecord::Single(sequence_number) => {
+				serializer.put_u8(Self::TYPE_SINGLE);
+				serializer.put_u24_le(sequence_number);
+			},
+			AcknowledgeRecord::Range { start, end } => {
+				serializer.put_u8(Self::TYPE_RANGE);
+				serializer.put_u24_le(start);
+				serializer.put_u24_le(end);
+			}
+		}
+	}
+}
+
+impl DecodeBody for AcknowledgeRecord {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(1)?;
+		if serializer.get_u8() == Self::TYPE_RANGE {
+			serializer.require(3 + 3)?;
+			let start = serializer.get_u24_le();
+			let end = serializer.get_u24_le();
+			if end < start {
+				return Err(DecodeError::InvalidAcknowledgeRange {
+					start,
+					end
+				});
+			}
+			Ok(Self::new(start, end))
+		} else {
+			serializer.require(3)?;
+			Ok(AcknowledgeRecord::Single(serializer.get_u24_le()))
+		}
+	}
+}
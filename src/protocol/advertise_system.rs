@@ -1,4 +1,4 @@
-use crate::protocol::{EncodeBody, DecodeBody, MessageIdentifiers, GetString, PutStr, CommonPacket, MessageIdentifierHeader};
+use crate::protocol::{EncodeBody, DecodeBody, MessageIdentifiers, GetString, PutStr, CommonPacket, DecodeError, MessageIdentifierHeader};
 use bytes::{BufMut, Buf};
 
 #[derive(Default, Debug)]
@@ -17,10 +17,10 @@ impl EncodeBody for AdvertiseSystem {
 }
 
 impl DecodeBody for AdvertiseSystem {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			server_name: serializer.get_string()
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self {
+			server_name: serializer.get_string()?
+		})
 	}
 }
 
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, DecodeBody, EncodeBody, MessageIdentifiers, PutRaknetTime, CommonPacket, GetRaknetTime};
+use crate::protocol::{MessageIdentifierHeader, DecodeBody, EncodeBody, MessageIdentifiers, PutRaknetTime, CommonPacket, GetRaknetTime, DecodeError};
 use bytes::{BufMut, Buf};
 use crate::RaknetTime;
 
@@ -18,10 +18,10 @@ impl EncodeBody for ConnectedPing {
 }
 
 impl DecodeBody for ConnectedPing {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			send_ping_time: serializer.get_raknet_time()
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self {
+			send_ping_time: serializer.get_raknet_time()?
+		})
 	}
 }
 
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, CommonPacket, GetRaknetTime, PutRaknetTime};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, CommonPacket, GetRaknetTime, PutRaknetTime, DecodeError};
 use bytes::{BufMut, Buf};
 use crate::RaknetTime;
 
@@ -20,11 +20,11 @@ impl EncodeBody for ConnectedPong {
 }
 
 impl DecodeBody for ConnectedPong {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			send_ping_time: serializer.get_raknet_time(),
-			send_pong_time: serializer.get_raknet_time()
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self {
+			send_ping_time: serializer.get_raknet_time()?,
+			send_pong_time: serializer.get_raknet_time()?
+		})
 	}
 }
 
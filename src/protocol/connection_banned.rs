@@ -0,0 +1,50 @@
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, OfflineMessage, OfflineMessageImpl, MessageIdentifiers, CommonPacket, DecodeError, Require};
+use bytes::{BufMut, Buf};
+
+/**
+ * Sent in reply to OpenConnectionRequest2 if the client's IP reached its connection limit
+ */
+#[derive(Default, Debug)]
+pub struct ConnectionBanned {
+	pub offline_message: OfflineMessage,
+	pub server_id: u64
+}
+
+impl OfflineMessageImpl for ConnectionBanned {
+	fn get_offline_message(&self) -> &OfflineMessage {
+		&self.offline_message
+	}
+}
+
+impl ConnectionBanned {
+	pub fn create(server_id: u64) -> Self {
+		Self {
+			offline_message: OfflineMessage::default(),
+			server_id
+		}
+	}
+}
+
+impl MessageIdentifierHeader for ConnectionBanned {
+	const ID: MessageIdentifiers = MessageIdentifiers::ConnectionBanned;
+}
+
+impl EncodeBody for ConnectionBanned {
+	fn encode_body(&self, serializer: &mut dyn BufMut) {
+		self.offline_message.encode_body(serializer);
+		serializer.put_u64(self.server_id);
+	}
+}
+
+impl DecodeBody for ConnectionBanned {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(8)?;
+		Ok(Self {
+			offline_message,
+			server_id: serializer.get_u64()
+		})
+	}
+}
+
+impl CommonPacket for ConnectionBanned {}
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifiers, MessageIdentifierHeader, EncodeBody, DecodeBody, PutRaknetTime, GetRaknetTime, CommonPacket};
+use crate::protocol::{MessageIdentifiers, MessageIdentifierHeader, EncodeBody, DecodeBody, PutRaknetTime, GetRaknetTime, CommonPacket, DecodeError, Require};
 use bytes::{BufMut, Buf};
 use crate::RaknetTime;
 
@@ -22,12 +22,16 @@ impl EncodeBody for ConnectionRequest {
 }
 
 impl DecodeBody for ConnectionRequest {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			client_id: serializer.get_u64(),
-			send_ping_time: serializer.get_raknet_time(),
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(8)?;
+		let client_id = serializer.get_u64();
+		let send_ping_time = serializer.get_raknet_time()?;
+		serializer.require(1)?;
+		Ok(Self {
+			client_id,
+			send_ping_time,
 			use_security: serializer.get_u8() != 0
-		}
+		})
 	}
 }
 
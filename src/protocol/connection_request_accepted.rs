@@ -1,5 +1,5 @@
-use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, PutAddress, GetAddress, GetRaknetTime, PutRaknetTime, MessageIdentifiers, CommonPacket};
+use std::net::SocketAddr;
+use crate::protocol::{unassigned_address, MessageIdentifierHeader, EncodeBody, DecodeBody, PutAddress, GetAddress, GetRaknetTime, PutRaknetTime, MessageIdentifiers, CommonPacket, DecodeError, Require};
 use bytes::{BufMut, Buf};
 use crate::{SYSTEM_ADDRESS_COUNT, RaknetTime};
 
@@ -31,7 +31,7 @@ impl EncodeBody for ConnectionRequestAccepted {
 		serializer.put_address(&self.address);
 		serializer.put_u16(0);
 
-		let dummy = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0));
+		let dummy = unassigned_address(&self.address);
 
 		for i in 0..SYSTEM_ADDRESS_COUNT {
 			serializer.put_address(self.system_addresses.get(i).unwrap_or(&dummy));
@@ -43,29 +43,30 @@ impl EncodeBody for ConnectionRequestAccepted {
 }
 
 impl DecodeBody for ConnectionRequestAccepted {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		let address = serializer.get_address();
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let address = serializer.get_address()?;
+		serializer.require(2)?;
 		serializer.get_u16(); // TODO check this
 
-		let dummy = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0));
+		let dummy = unassigned_address(&address);
 
 		let mut system_addresses = Vec::new();
 		for _i in 0..SYSTEM_ADDRESS_COUNT {
 			system_addresses.push(
 				if serializer.remaining() > 16 {
-					serializer.get_address()
+					serializer.get_address()?
 				} else {
 					dummy.clone()
 				}
 			)
 		}
 
-		Self {
+		Ok(Self {
 			address,
 			system_addresses,
-			send_ping_time: serializer.get_raknet_time(),
-			send_pong_time: serializer.get_raknet_time()
-		}
+			send_ping_time: serializer.get_raknet_time()?,
+			send_pong_time: serializer.get_raknet_time()?
+		})
 	}
 }
 
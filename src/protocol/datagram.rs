@@ -1,5 +1,6 @@
-use crate::protocol::{EncodeBody, DecodeBody, EncapsulatedPacket, EncodeHeader, CommonEncodePacket, DecodePacket};
+use crate::protocol::{EncodeBody, DecodeBody, EncapsulatedPacket, EncodeHeader, CommonEncodePacket, DecodePacket, DecodeError, Require};
 use bytes::{BufMut, Buf};
+use bytes_addition::{PutTriad, GetTriad};
 
 #[derive(Default, Debug)]
 pub struct Datagram {
@@ -22,24 +23,144 @@ impl Datagram {
 	pub const FLAG_NEEDS_B_AND_AS: u8 = 0x04;
 
 	pub const HEADER_SIZE: usize = 1 + 3; //header flags (1) + sequence number (3)
+
+	pub fn get_length(&self) -> usize {
+		Self::HEADER_SIZE + self.packets.iter().map(| packet | packet.get_total_length()).sum::<usize>()
+	}
 }
 
 impl EncodeHeader for Datagram {
 	fn encode_header(&self) -> u8 {
-		unimplemented!()
+		Self::FLAG_VALID | self.header_flags
 	}
 }
 
 impl EncodeBody for Datagram {
-	fn encode_body(&self, mut _serializer: &mut dyn BufMut) {
-		unimplemented!()
+	fn encode_body(&self, serializer: &mut dyn BufMut) {
+		serializer.put_u24_le(self.sequence_number.unwrap());
+		for packet in &self.packets {
+			packet.encode_body(serializer);
+		}
 	}
 }
 
 impl CommonEncodePacket for Datagram {}
 
 impl DecodePacket for Datagram {
-	fn decode_packet(serializer: &mut dyn Buf) -> Self {
-		unimplemented!()
+	fn decode_packet(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(Self::HEADER_SIZE)?;
+		let header_flags = serializer.get_u8();
+		let sequence_number = serializer.get_u24_le();
+		let mut packets = Vec::new();
+		while serializer.has_remaining() {
+			packets.push(Box::new(EncapsulatedPacket::decode_body(serializer)?));
+		}
+		Ok(Self {
+			header_flags,
+			packets,
+			sequence_number: Some(sequence_number)
+		})
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::protocol::{Datagram, DecodePacket, EncodePacket, PacketReliability, DecodeError};
+
+	// ConnectionRequest from a vanilla client, reliable ordered on channel 0
+	const CONNECTION_REQUEST: [u8; 32] = [
+		0x84, 0x00, 0x00, 0x00,
+		0x60, 0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		0x09, 0x3a, 0x5f, 0x8c, 0x01, 0xd2, 0x46, 0x7b, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2f, 0x64, 0x00
+	];
+
+	// first two parts of a split game packet, reliable ordered, split id 3
+	const SPLIT_PARTS: [u8; 53] = [
+		0x84, 0x2a, 0x01, 0x00,
+		0x70, 0x00, 0x30, 0x15, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00,
+		0xfe, 0x01, 0x02, 0x03, 0x04, 0x05,
+		0x70, 0x00, 0x18, 0x16, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+		0x00, 0x00, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01,
+		0x06, 0x07, 0x08
+	];
+
+	// ConnectedPing sent unreliable
+	const CONNECTED_PING: [u8; 16] = [
+		0x80, 0xff, 0xff, 0xff,
+		0x00, 0x00, 0x48,
+		0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x88
+	];
+
+	fn round_trip(raw: &[u8]) -> Datagram {
+		let datagram = Datagram::decode_packet(&mut &raw[..]).unwrap();
+		let mut encoded = Vec::new();
+		datagram.encode_packet(&mut encoded);
+		assert_eq!(encoded.as_slice(), raw);
+		datagram
+	}
+
+	#[test]
+	fn connection_request() {
+		let datagram = round_trip(&CONNECTION_REQUEST);
+		assert_eq!(datagram.header_flags, Datagram::FLAG_VALID | Datagram::FLAG_NEEDS_B_AND_AS);
+		assert_eq!(datagram.sequence_number, Some(0));
+		assert_eq!(datagram.packets.len(), 1);
+
+		let packet = &datagram.packets[0];
+		assert!(matches!(packet.reliability, PacketReliability::ReliableOrdered));
+		assert_eq!(packet.message_index, Some(0));
+		assert_eq!(packet.order_index, Some(0));
+		assert_eq!(packet.order_channel, Some(0));
+		assert_eq!(packet.buffer.len(), 18);
+		assert_eq!(packet.buffer[0], 0x09);
+	}
+
+	#[test]
+	fn split_parts() {
+		let datagram = round_trip(&SPLIT_PARTS);
+		assert_eq!(datagram.sequence_number, Some(0x012a));
+		assert_eq!(datagram.packets.len(), 2);
+
+		for (i, packet) in datagram.packets.iter().enumerate() {
+			let split_info = packet.split_info.as_ref().unwrap();
+			assert_eq!(split_info.get_id(), 3);
+			assert_eq!(split_info.get_part_index(), i as u32);
+			assert_eq!(split_info.get_total_part_count(), 2);
+			assert_eq!(packet.message_index, Some(0x15 + i as u32));
+			assert_eq!(packet.order_index, Some(2));
+		}
+		assert_eq!(datagram.packets[0].buffer, vec![0xfe, 0x01, 0x02, 0x03, 0x04, 0x05]);
+		assert_eq!(datagram.packets[1].buffer, vec![0x06, 0x07, 0x08]);
+	}
+
+	#[test]
+	fn unreliable_ping() {
+		let datagram = round_trip(&CONNECTED_PING);
+		assert_eq!(datagram.header_flags, Datagram::FLAG_VALID);
+		assert_eq!(datagram.sequence_number, Some(0xffffff));
+		assert_eq!(datagram.packets.len(), 1);
+		assert!(matches!(datagram.packets[0].reliability, PacketReliability::Unreliable));
+		assert_eq!(datagram.packets[0].message_index, None);
+		assert_eq!(datagram.packets[0].buffer.len(), 9);
+	}
+
+	#[test]
+	fn malformed() {
+		for len in 0..CONNECTION_REQUEST.len() {
+			if len == Datagram::HEADER_SIZE {
+				//a datagram without any encapsulated packets is still well formed
+				continue;
+			}
+			assert!(matches!(
+				Datagram::decode_packet(&mut &CONNECTION_REQUEST[..len]),
+				Err(DecodeError::Truncated { .. })
+			));
+		}
+
+		let mut empty = CONNECTED_PING;
+		empty[5] = 0x00;
+		empty[6] = 0x00;
+		assert_eq!(Datagram::decode_packet(&mut &empty[..]).unwrap_err(), DecodeError::EmptyPayload);
+	}
+}
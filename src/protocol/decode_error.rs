@@ -0,0 +1,50 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DecodeError {
+	/// The buffer ended before the packet was fully read
+	Truncated {
+		needed: usize,
+		remaining: usize
+	},
+	BadIdentifier {
+		expected: u8,
+		actual: u8
+	},
+	BadReliability(u8),
+	UnknownAddressVersion(u8),
+	BadAddressFamily(u16),
+	InvalidMagic,
+	/// Encapsulated packets must carry at least one byte of payload
+	EmptyPayload,
+	InvalidString,
+	InvalidAcknowledgeRange {
+		start: u32,
+		end: u32
+	},
+	/// The records of an ACK or NACK cover more than `AcknowledgePacket::MAX_SEQUENCE_NUMBERS` sequence numbers
+	TooManyAcknowledgements,
+	/// `OpenConnectionReply1` tells whether a cookie and a public key follow with this byte
+	BadCookieFlag(u8)
+}
+
+impl Display for DecodeError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DecodeError::Truncated { needed, remaining } => write!(f, "buffer underflow: needed {} bytes, {} remaining", needed, remaining),
+			DecodeError::BadIdentifier { expected, actual } => write!(f, "message identifier doesn't match: expected {:#04x}, got {:#04x}", expected, actual),
+			DecodeError::BadReliability(reliability) => write!(f, "unknown packet reliability {}", reliability),
+			DecodeError::UnknownAddressVersion(version) => write!(f, "unknown ip version: {}", version),
+			DecodeError::BadAddressFamily(family) => write!(f, "{}: not AF_INET6", family),
+			DecodeError::InvalidMagic => write!(f, "invalid offline message magic"),
+			DecodeError::EmptyPayload => write!(f, "encapsulated packet length cannot be zero"),
+			DecodeError::InvalidString => write!(f, "failed to parse as utf8"),
+			DecodeError::InvalidAcknowledgeRange { start, end } => write!(f, "acknowledge range ends before it starts: {}-{}", start, end),
+			DecodeError::TooManyAcknowledgements => write!(f, "too many acknowledged sequence numbers"),
+			DecodeError::BadCookieFlag(flag) => write!(f, "unknown cookie flag {}", flag)
+		}
+	}
+}
+
+impl Error for DecodeError {}
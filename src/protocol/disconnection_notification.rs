@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, CommonPacket, MessageIdentifiers};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, CommonPacket, MessageIdentifiers, DecodeError};
 use bytes::{BufMut, Buf};
 
 #[derive(Default, Debug)]
@@ -15,8 +15,8 @@ impl EncodeBody for DisconnectionNotification {
 }
 
 impl DecodeBody for DisconnectionNotification {
-	fn decode_body(_serializer: &mut dyn Buf) -> Self {
-		Self
+	fn decode_body(_serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self)
 	}
 }
 
@@ -1,4 +1,4 @@
-use crate::protocol::{EncodeBody, DecodeBody};
+use crate::protocol::{EncodeBody, DecodeBody, DecodeError, Require};
 use bytes::{BufMut, Buf};
 use crate::protocol::PacketReliability;
 use bytes_addition::{PutTriad, GetTriad};
@@ -64,37 +64,46 @@ impl EncodeBody for EncapsulatedPacket {
 }
 
 impl DecodeBody for EncapsulatedPacket {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
 		let mut packet = EncapsulatedPacket::default();
+		serializer.require(1 + 2)?;
 		let flags = serializer.get_u8();
-		packet.reliability = ((flags & Self::RELIABILITY_FLAGS) >> Self::RELIABILITY_SHIFT).try_into().expect("failed to decode packet reliability");
+		let reliability = (flags & Self::RELIABILITY_FLAGS) >> Self::RELIABILITY_SHIFT;
+		packet.reliability = reliability.try_into().map_err(| _ | DecodeError::BadReliability(reliability))?;
+		if packet.reliability.has_ack_receipt() {
+			return Err(DecodeError::BadReliability(reliability));
+		}
 		let has_split = (flags & Self::SPLIT_FLAG) != 0;
 
 		let length = (serializer.get_u16() as f32 / 8f32).ceil() as u16;
 		if length == 0 {
-			panic!("Encapsulated packet length cannot be zero"); // todo implement Error
+			return Err(DecodeError::EmptyPayload);
 		}
 
 		if packet.reliability.is_reliable() {
+			serializer.require(3)?;
 			packet.message_index = Some(serializer.get_u24_le());
 		}
 
 		if packet.reliability.is_sequenced() {
+			serializer.require(3)?;
 			packet.sequence_index = Some(serializer.get_u24_le());
 		}
 
 		if packet.reliability.is_sequenced() || packet.reliability.is_ordered() {
+			serializer.require(3 + 1)?;
 			packet.order_index = Some(serializer.get_u24_le());
 			packet.order_channel = Some(serializer.get_u8());
 		}
 
 		if has_split {
-			packet.split_info.replace(SplitPacketInfo::decode_body(serializer));
+			packet.split_info.replace(SplitPacketInfo::decode_body(serializer)?);
 		}
 
+		serializer.require(length as usize)?;
 		packet.buffer = vec![0; length as usize];
 		serializer.copy_to_slice(&mut packet.buffer);
 
-		packet
+		Ok(packet)
 	}
-}
\ No newline at end of file
+}
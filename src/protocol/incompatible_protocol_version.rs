@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, OfflineMessage, OfflineMessageImpl, MessageIdentifiers, CommonPacket};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, OfflineMessage, OfflineMessageImpl, MessageIdentifiers, CommonPacket, DecodeError, Require};
 use bytes::{BufMut, Buf};
 
 #[derive(Default, Debug)]
@@ -37,12 +37,16 @@ impl EncodeBody for IncompatibleProtocolVersion {
 }
 
 impl DecodeBody for IncompatibleProtocolVersion {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			protocol_version: serializer.get_u8(),
-			offline_message: OfflineMessage::decode_body(serializer),
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(1)?;
+		let protocol_version = serializer.get_u8();
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(8)?;
+		Ok(Self {
+			protocol_version,
+			offline_message,
 			server_id: serializer.get_u64()
-		}
+		})
 	}
 }
 
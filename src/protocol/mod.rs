@@ -1,23 +1,29 @@
 mod ack;
 mod acknowledge_packet;
+mod acknowledge_record;
 mod advertise_system;
 mod connected_ping;
 mod connected_pong;
+mod connection_banned;
 mod connection_request;
 mod connection_request_accepted;
 mod datagram;
+mod decode_error;
 mod disconnection_notification;
 mod encapsulated_packet;
 mod incompatible_protocol_version;
+mod ip_recently_connected;
 mod message_identifiers;
 mod nack;
 mod new_incoming_connection;
+mod no_free_incoming_connections;
 mod offline_message;
 mod open_connection_reply1;
 mod open_connection_reply2;
 mod open_connection_request1;
 mod open_connection_request2;
 mod packet_reliability;
+mod remote_system_requires_pub_key;
 mod split_packet_info;
 mod unconnected_ping;
 mod unconnected_ping_open_connections;
@@ -25,24 +31,30 @@ mod unconnected_pong;
 
 pub use ack::ACK;
 pub use acknowledge_packet::AcknowledgePacket;
+pub use acknowledge_record::AcknowledgeRecord;
 pub use advertise_system::AdvertiseSystem;
 pub use connected_ping::ConnectedPing;
 pub use connected_pong::ConnectedPong;
+pub use connection_banned::ConnectionBanned;
 pub use connection_request::ConnectionRequest;
 pub use connection_request_accepted::ConnectionRequestAccepted;
 pub use datagram::Datagram;
+pub use decode_error::DecodeError;
 pub use disconnection_notification::DisconnectionNotification;
 pub use encapsulated_packet::EncapsulatedPacket;
 pub use incompatible_protocol_version::IncompatibleProtocolVersion;
+pub use ip_recently_connected::IpRecentlyConnected;
 pub use message_identifiers::MessageIdentifiers;
 pub use nack::NACK;
 pub use new_incoming_connection::NewIncomingConnection;
+pub use no_free_incoming_connections::NoFreeIncomingConnections;
 pub use offline_message::*;
 pub use open_connection_reply1::OpenConnectionReply1;
 pub use open_connection_reply2::OpenConnectionReply2;
 pub use open_connection_request1::OpenConnectionRequest1;
 pub use open_connection_request2::OpenConnectionRequest2;
 pub use packet_reliability::PacketReliability;
+pub use remote_system_requires_pub_key::RemoteSystemRequiresPubKey;
 pub use split_packet_info::SplitPacketInfo;
 pub use unconnected_ping::UnconnectedPing;
 pub use unconnected_ping_open_connections::UnconnectedPingOpenConnections;
@@ -91,14 +103,19 @@ impl<T: CommonEncodePacket> EncodePacket for T {
 
 pub trait CommonEncodePacket: EncodeHeader + EncodeBody {} //flag
 
-pub trait DecodePacket {
-	fn decode_packet(serializer: &mut dyn Buf) -> Self;
+pub trait DecodePacket: Sized {
+	fn decode_packet(serializer: &mut dyn Buf) -> Result<Self, DecodeError>;
 }
 
 impl<T: CommonDecodePacket> DecodePacket for T {
-	fn decode_packet(serializer: &mut dyn Buf) -> Self {
-		if T::ID as u8 != serializer.get_u8() {
-			panic!("message identifier doesn't match");
+	fn decode_packet(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(1)?;
+		let id = serializer.get_u8();
+		if T::ID as u8 != id {
+			return Err(DecodeError::BadIdentifier {
+				expected: T::ID as u8,
+				actual: id
+			});
 		}
 		Self::decode_body(serializer)
 	}
@@ -114,8 +131,8 @@ pub trait EncodeBody {
 	fn encode_body(&self, serializer: &mut dyn BufMut);
 }
 
-pub trait DecodeBody {
-	fn decode_body(serializer: &mut dyn Buf) -> Self;
+pub trait DecodeBody: Sized {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError>;
 }
 
 pub trait MessageIdentifierHeader {
@@ -128,9 +145,38 @@ impl<T: MessageIdentifierHeader> EncodeHeader for T {
 	}
 }
 
+trait Require {
+	fn require(&self, len: usize) -> Result<(), DecodeError>;
+}
+
+impl<T: Buf + ?Sized> Require for T {
+	fn require(&self, len: usize) -> Result<(), DecodeError> {
+		if self.remaining() < len {
+			Err(DecodeError::Truncated {
+				needed: len,
+				remaining: self.remaining()
+			})
+		} else {
+			Ok(())
+		}
+	}
+}
+
+//sockaddr_in6 is copied as is, so the family depends on the sender's platform (Linux, Windows, macOS, FreeBSD, OpenBSD)
+const AF_INET6_VALUES: [u16; 5] = [10, 23, 30, 28, 24];
+
+/**
+ * Placeholder for unused system addresses, of the same family as the given address
+ */
+pub(crate) fn unassigned_address(family: &SocketAddr) -> SocketAddr {
+	match family {
+		SocketAddr::V4(_) => SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
+		SocketAddr::V6(_) => SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0))
+	}
+}
 
 trait GetAddress {
-	fn get_address(&mut self) -> SocketAddr;
+	fn get_address(&mut self) -> Result<SocketAddr, DecodeError>;
 }
 
 trait PutAddress {
@@ -138,9 +184,11 @@ trait PutAddress {
 }
 
 impl<T: Buf + ?Sized> GetAddress for T {
-	fn get_address(&mut self) -> SocketAddr {
-		match self.get_u8() {
+	fn get_address(&mut self) -> Result<SocketAddr, DecodeError> {
+		self.require(1)?;
+		Ok(match self.get_u8() {
 			4 => {
+				self.require(4 + 2)?;
 				let mut raw_ip_bytes: [u8; 4] = [0; 4];
 				for i in 0..4 {
 					raw_ip_bytes[i] = !self.get_u8();
@@ -155,8 +203,11 @@ impl<T: Buf + ?Sized> GetAddress for T {
 				)
 			},
 			6 => {
+				self.require(2 + 2 + 4 + 16 + 4)?;
 				let af = self.get_u16_le();
-				assert_eq!(af, AF_INET6 as u16, "{}: not AF_INET6", af);
+				if !AF_INET6_VALUES.contains(&af) {
+					return Err(DecodeError::BadAddressFamily(af));
+				}
 				let port = self.get_u16();
 				let flow_info = self.get_u32();
 				let mut raw_ip_bytes: [u8; 16] = [0; 16];
@@ -165,8 +216,8 @@ impl<T: Buf + ?Sized> GetAddress for T {
 				let scope_id = self.get_u32();
 				SocketAddr::V6(SocketAddrV6::new(ip, port, flow_info, scope_id))
 			},
-			v => panic!("unknown ip version: {}", v)
-		}
+			v => return Err(DecodeError::UnknownAddressVersion(v))
+		})
 	}
 }
 
@@ -174,12 +225,14 @@ impl<T: BufMut + ?Sized> PutAddress for T {
 	fn put_address(&mut self, address: &SocketAddr) {
 		match address {
 			SocketAddr::V4(addr) => {
+				self.put_u8(4);
 				for x in addr.ip().octets().iter() {
 					self.put_u8(!*x)
 				}
 				self.put_u16(addr.port());
 			},
 			SocketAddr::V6(addr) => {
+				self.put_u8(6);
 				self.put_u16_le(AF_INET6 as u16);
 				self.put_u16(addr.port());
 				self.put_u32(addr.flowinfo());
@@ -191,7 +244,7 @@ impl<T: BufMut + ?Sized> PutAddress for T {
 }
 
 trait GetString {
-	fn get_string(&mut self) -> String;
+	fn get_string(&mut self) -> Result<String, DecodeError>;
 }
 
 trait PutStr {
@@ -199,10 +252,12 @@ trait PutStr {
 }
 
 impl<T: Buf + ?Sized> GetString for T {
-	fn get_string(&mut self) -> String {
+	fn get_string(&mut self) -> Result<String, DecodeError> {
+		self.require(2)?;
 		let length = self.get_u16() as usize;
+		self.require(length)?;
 		let bytes = self.copy_to_bytes(length);
-		String::from_utf8(bytes.to_vec()).expect("failed to parse as utf8")
+		String::from_utf8(bytes.to_vec()).map_err(| _ | DecodeError::InvalidString)
 	}
 }
 
@@ -215,7 +270,7 @@ impl<T: BufMut + ?Sized> PutStr for T {
 }
 
 trait GetRaknetTime {
-	fn get_raknet_time(&mut self) -> RaknetTime;
+	fn get_raknet_time(&mut self) -> Result<RaknetTime, DecodeError>;
 }
 
 
@@ -224,8 +279,9 @@ trait PutRaknetTime {
 }
 
 impl<T: Buf + ?Sized> GetRaknetTime for T {
-	fn get_raknet_time(&mut self) -> RaknetTime {
-		RaknetTime::from_millis(self.get_u64())
+	fn get_raknet_time(&mut self) -> Result<RaknetTime, DecodeError> {
+		self.require(8)?;
+		Ok(RaknetTime::from_millis(self.get_u64()))
 	}
 }
 
@@ -235,3 +291,102 @@ impl<T: BufMut + ?Sized> PutRaknetTime for T {
 	}
 }
 
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use std::net::SocketAddr;
+	use std::time::Duration;
+	use crate::protocol::{GetAddress, PutAddress, EncodePacket, DecodePacket, DecodeError, OpenConnectionRequest2, OpenConnectionReply2, NewIncomingConnection, ConnectionRequestAccepted};
+	use crate::SYSTEM_ADDRESS_COUNT;
+
+	const ADDRESSES: [&str; 4] = ["127.0.0.1:19132", "[::1]:19133", "[2001:db8::7]:1", "[fe80::1%3]:65535"];
+
+	pub(crate) fn addresses() -> Vec<SocketAddr> {
+		ADDRESSES.iter().map(| x | x.parse().unwrap()).collect()
+	}
+
+	pub(crate) fn round_trip<T: EncodePacket + DecodePacket>(packet: &T) -> T {
+		let mut buffer = Vec::new();
+		packet.encode_packet(&mut buffer);
+		let decoded = T::decode_packet(&mut buffer.as_slice()).unwrap();
+		let mut encoded = Vec::new();
+		decoded.encode_packet(&mut encoded);
+		assert_eq!(encoded, buffer);
+		decoded
+	}
+
+	#[test]
+	fn address() {
+		for address in addresses() {
+			let mut buffer = Vec::new();
+			buffer.put_address(&address);
+			assert_eq!(buffer.len(), if address.is_ipv4() { 7 } else { 29 });
+			assert_eq!(buffer[0], if address.is_ipv4() { 4 } else { 6 });
+			let mut slice = buffer.as_slice();
+			assert_eq!(slice.get_address().unwrap(), address);
+			assert!(slice.is_empty());
+		}
+
+		//sockaddr_in6 written on Windows
+		let mut buffer = Vec::new();
+		buffer.put_address(&"[::1]:19133".parse().unwrap());
+		buffer[1] = 23;
+		assert_eq!(buffer.as_slice().get_address().unwrap(), "[::1]:19133".parse().unwrap());
+		buffer[1] = 2;
+		assert_eq!(buffer.as_slice().get_address().unwrap_err(), DecodeError::BadAddressFamily(2));
+	}
+
+	#[test]
+	fn open_connection_request2() {
+		for address in addresses() {
+			let packet = round_trip(&OpenConnectionRequest2 {
+				offline_message: Default::default(),
+				cookie: None,
+				challenge: None,
+				client_id: 7,
+				server_address: address,
+				mtu_size: 1400
+			});
+			assert_eq!(packet.server_address, address);
+			assert_eq!(packet.mtu_size, 1400);
+		}
+	}
+
+	#[test]
+	fn open_connection_reply2() {
+		for address in addresses() {
+			let packet = round_trip(&OpenConnectionReply2::create(42, address, 1400, None));
+			assert_eq!(packet.client_address, address);
+			assert_eq!(packet.mtu_size, 1400);
+		}
+	}
+
+	#[test]
+	fn new_incoming_connection() {
+		for address in addresses() {
+			let packet = round_trip(&NewIncomingConnection {
+				address,
+				system_addresses: addresses(),
+				send_ping_time: Duration::from_millis(1),
+				send_pong_time: Duration::from_millis(2)
+			});
+			assert_eq!(packet.address, address);
+			assert_eq!(packet.system_addresses.len(), SYSTEM_ADDRESS_COUNT);
+			assert_eq!(&packet.system_addresses[..ADDRESSES.len()], addresses().as_slice());
+			assert!(packet.system_addresses[ADDRESSES.len()..].iter().all(| x | x.ip().is_unspecified() && x.is_ipv6() == address.is_ipv6()));
+			assert_eq!(packet.send_pong_time, Duration::from_millis(2));
+		}
+	}
+
+	#[test]
+	fn connection_request_accepted() {
+		for address in addresses() {
+			let packet = round_trip(&ConnectionRequestAccepted::create(address, vec![address], Duration::from_millis(1), Duration::from_millis(2)));
+			assert_eq!(packet.address, address);
+			assert_eq!(packet.system_addresses.len(), SYSTEM_ADDRESS_COUNT);
+			assert_eq!(packet.system_addresses[0], address);
+			assert!(packet.system_addresses[1..].iter().all(| x | x.ip().is_unspecified() && x.is_ipv6() == address.is_ipv6()));
+			assert_eq!(packet.send_ping_time, Duration::from_millis(1));
+		}
+	}
+}
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, AcknowledgePacket, CommonPacket};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, AcknowledgePacket, CommonPacket, DecodeError};
 use bytes::{BufMut, Buf};
 
 #[derive(Default, Debug, Deref, DerefMut)]
@@ -17,10 +17,10 @@ impl EncodeBody for NACK {
 }
 
 impl DecodeBody for NACK {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			acknowledge: AcknowledgePacket::decode_body(serializer)
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self {
+			acknowledge: AcknowledgePacket::decode_body(serializer)?
+		})
 	}
 }
 
@@ -1,5 +1,5 @@
-use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, GetRaknetTime, PutRaknetTime, GetAddress, CommonPacket, PutAddress};
+use std::net::SocketAddr;
+use crate::protocol::{unassigned_address, MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, GetRaknetTime, PutRaknetTime, GetAddress, CommonPacket, PutAddress, DecodeError};
 use bytes::{BufMut, Buf};
 use crate::{SYSTEM_ADDRESS_COUNT, RaknetTime};
 
@@ -18,8 +18,9 @@ impl MessageIdentifierHeader for NewIncomingConnection {
 impl EncodeBody for NewIncomingConnection {
 	fn encode_body(&self, serializer: &mut dyn BufMut) {
 		serializer.put_address(&self.address);
-		for x in &self.system_addresses {
-			serializer.put_address(x);
+		let dummy = unassigned_address(&self.address);
+		for i in 0..SYSTEM_ADDRESS_COUNT {
+			serializer.put_address(self.system_addresses.get(i).unwrap_or(&dummy));
 		}
 		serializer.put_raknet_time(&self.send_ping_time);
 		serializer.put_raknet_time(&self.send_pong_time);
@@ -27,27 +28,27 @@ impl EncodeBody for NewIncomingConnection {
 }
 
 impl DecodeBody for NewIncomingConnection {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		let address = serializer.get_address();
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let address = serializer.get_address()?;
 
 		// TODO hack
 		let mut system_addresses = Vec::new();
-		let dummy = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0));
+		let dummy = unassigned_address(&address);
 		for _i in 0..SYSTEM_ADDRESS_COUNT {
 			system_addresses.push(
 				if serializer.remaining() <= 16 {
 					dummy.clone()
 				} else {
-					serializer.get_address()
+					serializer.get_address()?
 				}
 			)
 		}
-		Self {
+		Ok(Self {
 			address,
 			system_addresses,
-			send_ping_time: serializer.get_raknet_time(),
-			send_pong_time: serializer.get_raknet_time()
-		}
+			send_ping_time: serializer.get_raknet_time()?,
+			send_pong_time: serializer.get_raknet_time()?
+		})
 	}
 }
 
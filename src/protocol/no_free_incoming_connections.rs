@@ -0,0 +1,50 @@
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, OfflineMessage, OfflineMessageImpl, MessageIdentifiers, CommonPacket, DecodeError, Require};
+use bytes::{BufMut, Buf};
+
+/**
+ * Sent in reply to OpenConnectionRequest2 if the server reached its connection limit
+ */
+#[derive(Default, Debug)]
+pub struct NoFreeIncomingConnections {
+	pub offline_message: OfflineMessage,
+	pub server_id: u64
+}
+
+impl OfflineMessageImpl for NoFreeIncomingConnections {
+	fn get_offline_message(&self) -> &OfflineMessage {
+		&self.offline_message
+	}
+}
+
+impl NoFreeIncomingConnections {
+	pub fn create(server_id: u64) -> Self {
+		Self {
+			offline_message: OfflineMessage::default(),
+			server_id
+		}
+	}
+}
+
+impl MessageIdentifierHeader for NoFreeIncomingConnections {
+	const ID: MessageIdentifiers = MessageIdentifiers::NoFreeIncomingConnections;
+}
+
+impl EncodeBody for NoFreeIncomingConnections {
+	fn encode_body(&self, serializer: &mut dyn BufMut) {
+		self.offline_message.encode_body(serializer);
+		serializer.put_u64(self.server_id);
+	}
+}
+
+impl DecodeBody for NoFreeIncomingConnections {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(8)?;
+		Ok(Self {
+			offline_message,
+			server_id: serializer.get_u64()
+		})
+	}
+}
+
+impl CommonPacket for NoFreeIncomingConnections {}
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::protocol::{EncodeBody, DecodeBody, PacketImpl};
+use crate::protocol::{EncodeBody, DecodeBody, PacketImpl, DecodeError, Require};
 use bytes::{BufMut, Buf};
 use downcast_rs::impl_downcast;
 
@@ -30,12 +30,16 @@ impl EncodeBody for OfflineMessage {
 }
 
 impl DecodeBody for OfflineMessage {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(16)?;
 		let mut magic = [0; 16];
 		serializer.copy_to_slice(&mut magic);
-		Self {
-			magic
+		if magic != Self::MAGIC {
+			return Err(DecodeError::InvalidMagic);
 		}
+		Ok(Self {
+			magic
+		})
 	}
 }
 
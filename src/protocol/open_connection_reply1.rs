@@ -1,20 +1,27 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, DecodeError, Require};
 use bytes::{BufMut, Buf};
 
 #[derive(Default, Debug)]
 pub struct OpenConnectionReply1 {
 	pub offline_message: OfflineMessage,
 	pub server_id: u64,
-	pub server_security: bool,
+	pub cookie: Option<u32>, //servers with security send a cookie, which has to be echoed in OpenConnectionRequest2
+	pub public_key: Option<[u8; 32]>, //only sent along a cookie
 	pub mtu_size: u16
 }
 
 impl OpenConnectionReply1 {
-	pub fn create(server_id: u64, server_security: bool, mtu_size: u16) -> Self {
+	//RakNet's hasSecurity flag, a cookie without a public key is only sent by servers using cookies without security
+	const NO_COOKIE: u8 = 0;
+	const COOKIE_WITH_PUBLIC_KEY: u8 = 1;
+	const COOKIE: u8 = 2;
+
+	pub fn create(server_id: u64, cookie: Option<u32>, public_key: Option<[u8; 32]>, mtu_size: u16) -> Self {
 		Self {
 			offline_message: Default::default(),
 			server_id,
-			server_security,
+			cookie,
+			public_key,
 			mtu_size
 		}
 	}
@@ -34,19 +41,50 @@ impl EncodeBody for OpenConnectionReply1 {
 	fn encode_body(&self, serializer: &mut dyn BufMut) {
 		self.offline_message.encode_body(serializer);
 		serializer.put_u64(self.server_id);
-		serializer.put_u8(self.server_security as u8);
+		match (self.cookie, &self.public_key) {
+			(Some(cookie), Some(public_key)) => {
+				serializer.put_u8(Self::COOKIE_WITH_PUBLIC_KEY);
+				serializer.put_u32(cookie);
+				serializer.put_slice(public_key);
+			},
+			(Some(cookie), None) => {
+				serializer.put_u8(Self::COOKIE);
+				serializer.put_u32(cookie);
+			},
+			(None, _) => serializer.put_u8(Self::NO_COOKIE)
+		}
 		serializer.put_u16(self.mtu_size);
 	}
 }
 
 impl DecodeBody for OpenConnectionReply1 {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			offline_message: OfflineMessage::decode_body(serializer),
-			server_id: serializer.get_u64(),
-			server_security: serializer.get_u8() != 0,
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(8 + 1)?;
+		let server_id = serializer.get_u64();
+		let (cookie, public_key) = match serializer.get_u8() {
+			Self::NO_COOKIE => (None, None),
+			Self::COOKIE => {
+				serializer.require(4)?;
+				(Some(serializer.get_u32()), None)
+			},
+			Self::COOKIE_WITH_PUBLIC_KEY => {
+				serializer.require(4 + 32)?;
+				let cookie = serializer.get_u32();
+				let mut public_key = [0; 32];
+				serializer.copy_to_slice(&mut public_key);
+				(Some(cookie), Some(public_key))
+			},
+			flag => return Err(DecodeError::BadCookieFlag(flag))
+		};
+		serializer.require(2)?;
+		Ok(Self {
+			offline_message,
+			server_id,
+			cookie,
+			public_key,
 			mtu_size: serializer.get_u16()
-		}
+		})
 	}
 }
 
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, GetAddress, PutAddress};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, GetAddress, PutAddress, DecodeError, Require};
 use std::net::SocketAddr;
 use bytes::{BufMut, Buf};
 
@@ -8,17 +8,17 @@ pub struct OpenConnectionReply2 {
 	pub server_id: u64,
 	pub client_address: SocketAddr,
 	pub mtu_size: u16,
-	pub server_security: bool
+	pub answer: Option<[u8; 32]> //answer to the challenge of OpenConnectionRequest2, proves that the server owns its key pair
 }
 
 impl OpenConnectionReply2 {
-	pub fn create(server_id: u64, client_address: SocketAddr, mtu_size: u16, server_security: bool) -> Self {
+	pub fn create(server_id: u64, client_address: SocketAddr, mtu_size: u16, answer: Option<[u8; 32]>) -> Self {
 		Self {
 			offline_message: Default::default(),
 			server_id,
 			client_address,
 			mtu_size,
-			server_security
+			answer
 		}
 	}
 }
@@ -39,19 +39,39 @@ impl EncodeBody for OpenConnectionReply2 {
 		serializer.put_u64(self.server_id);
 		serializer.put_address(&self.client_address);
 		serializer.put_u16(self.mtu_size);
-		serializer.put_u8(self.server_security as u8);
+		match &self.answer {
+			Some(answer) => {
+				serializer.put_u8(1);
+				serializer.put_slice(answer);
+			},
+			None => serializer.put_u8(0)
+		}
 	}
 }
 
 impl DecodeBody for OpenConnectionReply2 {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			offline_message: OfflineMessage::decode_body(serializer),
-			server_id: serializer.get_u64(),
-			client_address: serializer.get_address(),
-			mtu_size: serializer.get_u16(),
-			server_security: serializer.get_u8() != 0
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(8)?;
+		let server_id = serializer.get_u64();
+		let client_address = serializer.get_address()?;
+		serializer.require(2 + 1)?;
+		let mtu_size = serializer.get_u16();
+		let answer = if serializer.get_u8() != 0 {
+			serializer.require(32)?;
+			let mut answer = [0; 32];
+			serializer.copy_to_slice(&mut answer);
+			Some(answer)
+		} else {
+			None
+		};
+		Ok(Self {
+			offline_message,
+			server_id,
+			client_address,
+			mtu_size,
+			answer
+		})
 	}
 }
 
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, EncodePacket, EncodeHeader, DecodePacket};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, EncodePacket, EncodeHeader, DecodePacket, DecodeError, Require};
 
 use bytes::{BufMut, Buf};
 
@@ -9,6 +9,10 @@ pub struct OpenConnectionRequest1 {
 	pub mtu_size: u16
 }
 
+impl OpenConnectionRequest1 {
+	pub const HEADER_SIZE: usize = 1 + 16 + 1; //message identifier (1) + magic (16) + protocol (1)
+}
+
 impl OfflineMessageImpl for OpenConnectionRequest1 {
 	fn get_offline_message(&self) -> &OfflineMessage {
 		&self.offline_message
@@ -27,12 +31,14 @@ impl EncodeBody for OpenConnectionRequest1 {
 }
 
 impl DecodeBody for OpenConnectionRequest1 {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			offline_message: OfflineMessage::decode_body(serializer),
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(1)?;
+		Ok(Self {
+			offline_message,
 			protocol: serializer.get_u8(),
 			mtu_size: serializer.remaining() as u16
-		}
+		})
 	}
 }
 
@@ -40,20 +46,26 @@ impl EncodePacket for OpenConnectionRequest1 {
 	fn encode_packet(&self, serializer: &mut dyn BufMut) {
 		let mut serializer = serializer.limit(self.mtu_size as usize);
 		serializer.put_u8(self.encode_header());
-		self.encode_packet(&mut serializer);
+		self.encode_body(&mut serializer);
 		serializer.put_slice(&vec![0; serializer.remaining_mut()])
 	}
 }
 
 impl DecodePacket for OpenConnectionRequest1 {
-	fn decode_packet(serializer: &mut dyn Buf) -> Self {
+	fn decode_packet(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
 		let original = serializer.remaining();
-		if Self::ID as u8 != serializer.get_u8() {
-			panic!("message identifier doesn't match");
+		serializer.require(1)?;
+		let id = serializer.get_u8();
+		if Self::ID as u8 != id {
+			return Err(DecodeError::BadIdentifier {
+				expected: Self::ID as u8,
+				actual: id
+			});
 		}
-		let packet = Self::decode_body(serializer);
-		let read = original - serializer.remaining();
-		serializer.advance(packet.mtu_size as usize - read);
-		packet
+		let mut packet = Self::decode_body(serializer)?;
+		//the whole packet is padded up to the MTU size, so its length is the MTU size
+		packet.mtu_size = original as u16;
+		serializer.advance(serializer.remaining());
+		Ok(packet)
 	}
-}
\ No newline at end of file
+}
@@ -1,15 +1,63 @@
 use std::net::SocketAddr;
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, PutAddress, GetAddress, CommonPacket};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, PutAddress, GetAddress, CommonPacket, DecodeError, Require};
 use bytes::{BufMut, Buf};
 
 #[derive(Debug)]
 pub struct OpenConnectionRequest2 {
 	pub offline_message: OfflineMessage,
+	pub cookie: Option<u32>, //echoed from OpenConnectionReply1, only present if the server has security
+	pub challenge: Option<[u8; 32]>, //public key of the client for the key exchange, only sent along a cookie
 	pub client_id: u64,
 	pub server_address: SocketAddr,
 	pub mtu_size: u16
 }
 
+impl OpenConnectionRequest2 {
+	/**
+	 * The cookie can't be told apart from the address, so a server with security has to decode requests with this
+	 */
+	pub fn decode_with_cookie(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(1)?;
+		let id = serializer.get_u8();
+		if Self::ID as u8 != id {
+			return Err(DecodeError::BadIdentifier {
+				expected: Self::ID as u8,
+				actual: id
+			});
+		}
+		Self::decode_fields(serializer, true)
+	}
+
+	fn decode_fields(serializer: &mut dyn Buf, has_cookie: bool) -> Result<Self, DecodeError> {
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		let (cookie, challenge) = if has_cookie {
+			serializer.require(4 + 1)?;
+			let cookie = serializer.get_u32();
+			let challenge = if serializer.get_u8() != 0 { //client wrote challenge
+				serializer.require(32)?;
+				let mut challenge = [0; 32];
+				serializer.copy_to_slice(&mut challenge);
+				Some(challenge)
+			} else {
+				None
+			};
+			(Some(cookie), challenge)
+		} else {
+			(None, None)
+		};
+		let server_address = serializer.get_address()?;
+		serializer.require(2 + 8)?;
+		Ok(Self {
+			offline_message,
+			cookie,
+			challenge,
+			server_address,
+			mtu_size: serializer.get_u16(),
+			client_id: serializer.get_u64()
+		})
+	}
+}
+
 impl OfflineMessageImpl for OpenConnectionRequest2 {
 	fn get_offline_message(&self) -> &OfflineMessage {
 		&self.offline_message
@@ -23,6 +71,16 @@ impl MessageIdentifierHeader for OpenConnectionRequest2 {
 impl EncodeBody for OpenConnectionRequest2 {
 	fn encode_body(&self, serializer: &mut dyn BufMut) {
 		self.offline_message.encode_body(serializer);
+		if let Some(cookie) = self.cookie {
+			serializer.put_u32(cookie);
+			match &self.challenge {
+				Some(challenge) => {
+					serializer.put_u8(1);
+					serializer.put_slice(challenge);
+				},
+				None => serializer.put_u8(0)
+			}
+		}
 		serializer.put_address(&self.server_address);
 		serializer.put_u16(self.mtu_size);
 		serializer.put_u64(self.client_id)
@@ -30,13 +88,8 @@ impl EncodeBody for OpenConnectionRequest2 {
 }
 
 impl DecodeBody for OpenConnectionRequest2 {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			offline_message: OfflineMessage::decode_body(serializer),
-			server_address: serializer.get_address(),
-			mtu_size: serializer.get_u16(),
-			client_id: serializer.get_u64()
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Self::decode_fields(serializer, false)
 	}
 }
 
@@ -1,3 +1,4 @@
+use num_enum::TryFromPrimitive;
 
 #[derive(Debug, TryFromPrimitive, Clone, Copy)]
 #[repr(u8)]
@@ -42,6 +43,27 @@ impl PacketReliability {
 			_ => false,
 		}
 	}
+
+	pub fn has_ack_receipt(&self) -> bool {
+		match self {
+			PacketReliability::UnreliableWithAckReceipt => true,
+			PacketReliability::ReliableWithAckReceipt => true,
+			PacketReliability::ReliableOrderedWithAckReceipt => true,
+			_ => false
+		}
+	}
+
+	/**
+	 * The reliability the packet is sent with, receipts are tracked locally and never reach the wire
+	 */
+	pub fn to_wire(&self) -> PacketReliability {
+		match self {
+			PacketReliability::UnreliableWithAckReceipt => PacketReliability::Unreliable,
+			PacketReliability::ReliableWithAckReceipt => PacketReliability::Reliable,
+			PacketReliability::ReliableOrderedWithAckReceipt => PacketReliability::ReliableOrdered,
+			reliability => *reliability
+		}
+	}
 }
 
 impl Default for PacketReliability {
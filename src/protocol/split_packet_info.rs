@@ -1,4 +1,4 @@
-use crate::protocol::{EncodeBody, DecodeBody};
+use crate::protocol::{EncodeBody, DecodeBody, DecodeError, Require};
 use bytes::{BufMut, Buf};
 
 #[derive(Debug, Clone)]
@@ -43,11 +43,12 @@ impl EncodeBody for SplitPacketInfo {
 }
 
 impl DecodeBody for SplitPacketInfo {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		serializer.require(4 + 2 + 4)?;
+		Ok(Self {
 			total_part_count: serializer.get_u32(),
 			id: serializer.get_u16(),
 			part_index: serializer.get_u32()
-		}
+		})
 	}
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, GetRaknetTime, PutRaknetTime};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, GetRaknetTime, PutRaknetTime, DecodeError, Require};
 use bytes::{BufMut, Buf};
 use crate::RaknetTime;
 
@@ -28,12 +28,15 @@ impl EncodeBody for UnconnectedPing {
 }
 
 impl DecodeBody for UnconnectedPing {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			send_ping_time: serializer.get_raknet_time(),
-			offline_message: OfflineMessage::decode_body(serializer),
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let send_ping_time = serializer.get_raknet_time()?;
+		let offline_message = OfflineMessage::decode_body(serializer)?;
+		serializer.require(8)?;
+		Ok(Self {
+			send_ping_time,
+			offline_message,
 			client_id: serializer.get_u64()
-		}
+		})
 	}
 }
 
@@ -1,4 +1,4 @@
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, UnconnectedPing, CommonPacket, OfflineMessageImpl, OfflineMessage};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, UnconnectedPing, CommonPacket, OfflineMessageImpl, OfflineMessage, DecodeError};
 use bytes::{BufMut, Buf};
 
 #[derive(Debug, Deref, DerefMut)]
@@ -17,10 +17,10 @@ impl EncodeBody for UnconnectedPingOpenConnections {
 }
 
 impl DecodeBody for UnconnectedPingOpenConnections {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			unconnected_ping: UnconnectedPing::decode_body(serializer)
-		}
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		Ok(Self {
+			unconnected_ping: UnconnectedPing::decode_body(serializer)?
+		})
 	}
 }
 
@@ -28,6 +28,6 @@ impl CommonPacket for UnconnectedPingOpenConnections {}
 
 impl OfflineMessageImpl for UnconnectedPingOpenConnections {
 	fn get_offline_message(&self) -> &OfflineMessage {
-		self.get_offline_message()
+		self.unconnected_ping.get_offline_message()
 	}
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 
-use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, GetRaknetTime, GetString, PutRaknetTime, PutStr};
+use crate::protocol::{MessageIdentifierHeader, EncodeBody, DecodeBody, MessageIdentifiers, OfflineMessage, OfflineMessageImpl, CommonPacket, GetRaknetTime, GetString, PutRaknetTime, PutStr, DecodeError, Require};
 use bytes::{BufMut, Buf};
 use crate::RaknetTime;
 
@@ -42,13 +42,15 @@ impl EncodeBody for UnconnectedPong {
 }
 
 impl DecodeBody for UnconnectedPong {
-	fn decode_body(serializer: &mut dyn Buf) -> Self {
-		Self {
-			send_ping_time: serializer.get_raknet_time(),
+	fn decode_body(serializer: &mut dyn Buf) -> Result<Self, DecodeError> {
+		let send_ping_time = serializer.get_raknet_time()?;
+		serializer.require(8)?;
+		Ok(Self {
+			send_ping_time,
 			server_id: serializer.get_u64(),
-			offline_message: OfflineMessage::decode_body(serializer),
-			server_name: serializer.get_string()
-		}
+			offline_message: OfflineMessage::decode_body(serializer)?,
+			server_name: serializer.get_string()?
+		})
 	}
 }
 
@@ -0,0 +1,232 @@
+use std::future::poll_fn;
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::task::{Context, Poll};
+use log::debug;
+use tokio::io::{Interest, ReadBuf};
+use tokio::net::UdpSocket;
+use tokio::select;
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio::time::{interval, MissedTickBehavior};
+use tokio_util::sync::PollSender;
+use crate::server::{Server, ProtocolAcceptor, ServerInterface, ServerEventStream, ServerConfig, ServerSocket};
+use crate::server::ipc::{UserToRaknetMessage, UserToRaknetMessageReceiver};
+use crate::server::server_event_stream::ServerEventStreamSender;
+
+/**
+ * Server driven by a tokio runtime instead of a dedicated receive thread.
+ * Events are delivered through a `ServerEventStream` and commands are accepted through a `PollSender` sink.
+ */
+pub struct AsyncServer {
+	server: Server<'static>,
+	sockets: Vec<UdpSocket>, //same order as the sockets of the server
+	next_socket: usize, //the sockets are polled round robin, so a busy one can't starve the others
+	commands: Receiver<UserToRaknetMessage>
+}
+
+impl AsyncServer {
+
+	const COMMAND_CHANNEL_SIZE: usize = 1024;
+	const EVENT_CHANNEL_SIZE: usize = 4096;
+
+	/**
+	 * Must be called within a tokio runtime.
+	 */
+	pub fn new(
+		server_id: u64,
+		udp_socket: std::net::UdpSocket,
+		config: ServerConfig,
+		protocol_acceptor: impl ProtocolAcceptor + 'static
+	) -> io::Result<(Self, ServerEventStream, PollSender<UserToRaknetMessage>)> {
+		Self::with_sockets(server_id, vec![udp_socket], config, protocol_acceptor)
+	}
+
+	/**
+	 * Serves every socket like `Server::with_sockets`. Must be called within a tokio runtime.
+	 */
+	pub fn with_sockets(
+		server_id: u64,
+		udp_sockets: Vec<std::net::UdpSocket>,
+		config: ServerConfig,
+		protocol_acceptor: impl ProtocolAcceptor + 'static
+	) -> io::Result<(Self, ServerEventStream, PollSender<UserToRaknetMessage>)> {
+		let mut sockets = Vec::with_capacity(udp_sockets.len());
+		for udp_socket in &udp_sockets {
+			udp_socket.set_nonblocking(true)?;
+			sockets.push(UdpSocket::from_std(udp_socket.try_clone()?)?);
+		}
+		let (event_sender, event_receiver) = channel(Self::EVENT_CHANNEL_SIZE);
+		let dropped_events = Arc::new(AtomicUsize::new(0));
+		let (command_sender, command_receiver) = channel(Self::COMMAND_CHANNEL_SIZE);
+		let server = Server::with_sockets(
+			server_id,
+			udp_sockets,
+			config,
+			protocol_acceptor,
+			UserToRaknetMessageReceiver::new(Default::default()),
+			ServerEventStreamSender::new(event_sender, dropped_events.clone())
+		)?;
+		Ok((
+			Self {
+				server,
+				sockets,
+				next_socket: 0,
+				commands: command_receiver
+			},
+			ServerEventStream::new(event_receiver, dropped_events),
+			PollSender::new(command_sender)
+		))
+	}
+
+	/**
+	 * Runs until the server is shut down and every session is gone.
+	 */
+	pub async fn run(mut self) {
+		let mut ticker = interval(self.server.config.tick_interval());
+		ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		let mut buffer = vec![0; self.server.config.max_mtu_size()];
+		loop {
+			//replies are sent from the server's own handles of the sockets, those which would have blocked wait until the socket is writable
+			let has_pending = self.server.sockets.iter().any(ServerSocket::has_pending);
+			select! {
+				(socket_id, result) = poll_fn(| cx | Self::poll_receive(&self.sockets, self.next_socket, cx, &mut buffer)) => {
+					self.next_socket = (socket_id + 1) % self.sockets.len();
+					match result {
+						Ok((read, address)) => self.server.internal.lock().receive_packet(socket_id, address, &buffer[..read]),
+						Err(e) => debug!("{:?}", e)
+					}
+				},
+				result = poll_fn(| cx | Self::poll_flush(&self.sockets, &self.server.sockets, cx)), if has_pending => if let Err(e) = result {
+					debug!("{:?}", e)
+				},
+				_ = ticker.tick() => if self.tick() {
+					return;
+				}
+			}
+		}
+	}
+
+	/**
+	 * Receives from the first ready socket, starting at `first_socket`
+	 */
+	fn poll_receive(sockets: &[UdpSocket], first_socket: usize, cx: &mut Context<'_>, buffer: &mut [u8]) -> Poll<(usize, io::Result<(usize, SocketAddr)>)> {
+		for i in 0..sockets.len() {
+			let socket_id = (first_socket + i) % sockets.len();
+			let mut read_buffer = ReadBuf::new(buffer);
+			if let Poll::Ready(result) = sockets[socket_id].poll_recv_from(cx, &mut read_buffer) {
+				let read = read_buffer.filled().len();
+				return Poll::Ready((socket_id, result.map(| address | (read, address))));
+			}
+		}
+		Poll::Pending
+	}
+
+	/**
+	 * Flushes the pending datagrams of the first writable socket
+	 */
+	fn poll_flush(sockets: &[UdpSocket], server_sockets: &[ServerSocket], cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		for (socket, server_socket) in sockets.iter().zip(server_sockets).filter(| (_, server_socket) | server_socket.has_pending()) {
+			while let Poll::Ready(result) = socket.poll_send_ready(cx) {
+				//the readiness is only cleared when tokio sees the WouldBlock, so the next poll waits for the socket again
+				match result.and_then(| _ | socket.try_io(Interest::WRITABLE, || server_socket.flush_pending())) {
+					Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
+					result => return Poll::Ready(result)
+				}
+			}
+		}
+		Poll::Pending
+	}
+
+	/**
+	 * Returns true once the server is shut down
+	 */
+	fn tick(&mut self) -> bool {
+		let mut internal = self.server.internal.lock();
+		//commands are left queued once shutting down
+		while !internal.shutdown {
+			match self.commands.try_recv() {
+				Ok(message) => internal.handle_message(message),
+				Err(_) => break
+			}
+		}
+		internal.tick();
+		internal.is_shut_down()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use tokio::net::UdpSocket;
+	use tokio::time::timeout;
+	use crate::protocol::{UnconnectedPing, UnconnectedPong, OfflineMessage, EncodePacket, DecodePacket};
+	use crate::server::{AsyncServer, ProtocolAcceptor, ServerConfig};
+	use crate::server::ipc::UserToRaknetMessage;
+
+	struct PA;
+
+	impl ProtocolAcceptor for PA {
+		fn accepts(&self, version: u8) -> bool {
+			version == 10
+		}
+
+		fn get_primary_version(&self) -> u8 {
+			10
+		}
+	}
+
+	#[tokio::test]
+	async fn ping() {
+		let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+		let server_address = socket.local_addr().unwrap();
+		let (server, _events, commands) = AsyncServer::new(1234, socket, ServerConfig::default(), PA).unwrap();
+		tokio::spawn(server.run());
+		commands.get_ref().unwrap().send(UserToRaknetMessage::SetName("async".to_owned())).await.unwrap();
+
+		let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let mut buffer = Vec::new();
+		UnconnectedPing {
+			offline_message: OfflineMessage::default(),
+			send_ping_time: Duration::from_millis(42),
+			client_id: 1
+		}.encode_packet(&mut buffer);
+		//the name is applied on the next tick
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		client.send_to(&buffer, server_address).await.unwrap();
+
+		let mut receive_buffer = [0; 1500];
+		let (read, _) = timeout(Duration::from_secs(1), client.recv_from(&mut receive_buffer)).await.unwrap().unwrap();
+		let pong = UnconnectedPong::decode_packet(&mut &receive_buffer[..read]).unwrap();
+		assert_eq!(pong.server_id, 1234);
+		assert_eq!(pong.server_name, "async");
+		assert_eq!(pong.send_ping_time, Duration::from_millis(42));
+	}
+
+	#[tokio::test]
+	async fn multiple_sockets() {
+		let sockets: Vec<_> = (0..2).map(| _ | std::net::UdpSocket::bind("127.0.0.1:0").unwrap()).collect();
+		let server_addresses: Vec<_> = sockets.iter().map(| socket | socket.local_addr().unwrap()).collect();
+		let (server, _events, _commands) = AsyncServer::with_sockets(1234, sockets, ServerConfig::default(), PA).unwrap();
+		tokio::spawn(server.run());
+
+		let mut buffer = Vec::new();
+		UnconnectedPing {
+			offline_message: OfflineMessage::default(),
+			send_ping_time: Duration::from_millis(42),
+			client_id: 1
+		}.encode_packet(&mut buffer);
+		for server_address in server_addresses {
+			let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+			client.send_to(&buffer, server_address).await.unwrap();
+			let mut receive_buffer = [0; 1500];
+			let (read, address) = timeout(Duration::from_secs(1), client.recv_from(&mut receive_buffer)).await.unwrap().unwrap();
+			//answered through the socket the ping arrived on
+			assert_eq!(address, server_address);
+			assert_eq!(UnconnectedPong::decode_packet(&mut &receive_buffer[..read]).unwrap().server_id, 1234);
+		}
+
+		assert!(AsyncServer::with_sockets(1234, vec![], ServerConfig::default(), PA).is_err());
+	}
+}
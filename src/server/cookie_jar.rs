@@ -0,0 +1,144 @@
+use std::net::{SocketAddr, IpAddr};
+use std::time::{Duration, Instant};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/**
+ * Issues the handshake cookies which prove that a client can receive packets at its source address.
+ * A cookie is an HMAC of the client address, the secret rotates so a cookie is valid for one to two rotation intervals.
+ */
+pub struct CookieJar {
+	secret: [u8; 32],
+
+	previous_secret: [u8; 32],
+
+	rotation_time: Instant,
+
+	rotation_interval: Duration
+}
+
+impl CookieJar {
+	pub fn new(rotation_interval: Duration) -> Self {
+		Self {
+			secret: Self::random_secret(),
+			previous_secret: Self::random_secret(),
+			rotation_time: Instant::now(),
+			rotation_interval
+		}
+	}
+
+	fn random_secret() -> [u8; 32] {
+		let mut secret = [0; 32];
+		getrandom::getrandom(&mut secret).expect("no random source for the cookie secret");
+		secret
+	}
+
+	fn rotate(&mut self) {
+		let elapsed = self.rotation_time.elapsed();
+		if elapsed < self.rotation_interval {
+			return;
+		}
+		self.previous_secret = if elapsed < self.rotation_interval * 2 {
+			self.secret
+		} else {
+			//cookies of the current secret expired as well
+			Self::random_secret()
+		};
+		self.secret = Self::random_secret();
+		self.rotation_time = Instant::now();
+	}
+
+	fn compute(secret: &[u8; 32], address: &SocketAddr) -> u32 {
+		let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+		match address.ip() {
+			IpAddr::V4(ip) => mac.update(&ip.octets()),
+			IpAddr::V6(ip) => mac.update(&ip.octets())
+		}
+		mac.update(&address.port().to_be_bytes());
+		let hash = mac.finalize().into_bytes();
+		u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+	}
+
+	pub fn generate(&mut self, address: &SocketAddr) -> u32 {
+		self.rotate();
+		Self::compute(&self.secret, address)
+	}
+
+	pub fn verify(&mut self, address: &SocketAddr, cookie: u32) -> bool {
+		self.rotate();
+		Self::compute(&self.secret, address) == cookie || Self::compute(&self.previous_secret, address) == cookie
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::SocketAddr;
+	use std::thread::sleep;
+	use std::time::Duration;
+	use crate::server::CookieJar;
+	use crate::protocol::{EncodePacket, DecodePacket, DecodeError, OpenConnectionReply1, OpenConnectionRequest2};
+	use crate::protocol::tests::{addresses, round_trip};
+
+	#[test]
+	fn verify() {
+		let mut cookies = CookieJar::new(Duration::from_secs(60));
+		let address: SocketAddr = "10.0.0.1:19132".parse().unwrap();
+		let cookie = cookies.generate(&address);
+		assert_eq!(cookies.generate(&address), cookie);
+		assert!(cookies.verify(&address, cookie));
+		assert!(!cookies.verify(&"10.0.0.1:19133".parse().unwrap(), cookie));
+		assert!(!cookies.verify(&"10.0.0.2:19132".parse().unwrap(), cookie));
+		assert_ne!(CookieJar::new(Duration::from_secs(60)).generate(&address), cookie);
+	}
+
+	#[test]
+	fn rotation() {
+		let mut cookies = CookieJar::new(Duration::from_millis(50));
+		let address: SocketAddr = "[::1]:19132".parse().unwrap();
+		let cookie = cookies.generate(&address);
+		sleep(Duration::from_millis(60));
+		//still accepted for one more interval
+		assert!(cookies.verify(&address, cookie));
+		assert_ne!(cookies.generate(&address), cookie);
+		sleep(Duration::from_millis(60));
+		assert!(!cookies.verify(&address, cookie));
+	}
+
+	#[test]
+	fn cookie_packets() {
+		let reply = round_trip(&OpenConnectionReply1::create(42, Some(0xdeadbeef), None, 1400));
+		assert_eq!(reply.cookie, Some(0xdeadbeef));
+		assert_eq!(reply.public_key, None);
+		assert_eq!(reply.mtu_size, 1400);
+		assert_eq!(round_trip(&OpenConnectionReply1::create(42, None, None, 1400)).cookie, None);
+
+		//trailing bytes aren't mistaken for a public key
+		let mut buffer = Vec::new();
+		OpenConnectionReply1::create(42, Some(0xdeadbeef), None, 1400).encode_packet(&mut buffer);
+		buffer.extend_from_slice(&[0; 34]);
+		let reply = OpenConnectionReply1::decode_packet(&mut buffer.as_slice()).unwrap();
+		assert_eq!(reply.public_key, None);
+		assert_eq!(reply.mtu_size, 1400);
+		//the flag follows the offline message magic (16 bytes) and the server id (8 bytes)
+		buffer[1 + 16 + 8] = 3;
+		assert_eq!(OpenConnectionReply1::decode_packet(&mut buffer.as_slice()).unwrap_err(), DecodeError::BadCookieFlag(3));
+
+		for address in addresses() {
+			let mut buffer = Vec::new();
+			OpenConnectionRequest2 {
+				offline_message: Default::default(),
+				cookie: Some(0xdeadbeef),
+				challenge: None,
+				client_id: 7,
+				server_address: address,
+				mtu_size: 1400
+			}.encode_packet(&mut buffer);
+			let request = OpenConnectionRequest2::decode_with_cookie(&mut buffer.as_slice()).unwrap();
+			assert_eq!(request.cookie, Some(0xdeadbeef));
+			assert_eq!(request.server_address, address);
+			assert_eq!(request.client_id, 7);
+			//a server without security doesn't expect a cookie
+			assert!(OpenConnectionRequest2::decode_packet(&mut buffer.as_slice()).is_err());
+		}
+	}
+}
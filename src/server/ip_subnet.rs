@@ -0,0 +1,95 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::fmt::{Display, Formatter};
+
+/**
+ * Network in CIDR notation, a single address is a /32 (IPv4) or /128 (IPv6) subnet
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct IpSubnet {
+	address: IpAddr,
+	prefix_len: u8
+}
+
+impl IpSubnet {
+	/**
+	 * Host bits of `address` are cleared, returns None if `prefix_len` is longer than the address
+	 */
+	pub fn new(address: IpAddr, prefix_len: u8) -> Option<Self> {
+		let address = match address {
+			IpAddr::V4(address) => {
+				if prefix_len > 32 {
+					return None;
+				}
+				IpAddr::from(Ipv4Addr::from(u32::from(address) & (u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0))))
+			},
+			IpAddr::V6(address) => {
+				if prefix_len > 128 {
+					return None;
+				}
+				IpAddr::from(Ipv6Addr::from(u128::from(address) & (u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0))))
+			}
+		};
+		Some(Self {
+			address,
+			prefix_len
+		})
+	}
+
+	pub fn get_address(&self) -> IpAddr {
+		self.address
+	}
+
+	pub fn get_prefix_len(&self) -> u8 {
+		self.prefix_len
+	}
+
+	pub fn contains(&self, address: &IpAddr) -> bool {
+		match (self.address, address) {
+			(IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+				Self::new(*address, self.prefix_len).is_some_and(| subnet | subnet.address == self.address)
+			},
+			_ => false
+		}
+	}
+}
+
+impl From<IpAddr> for IpSubnet {
+	fn from(address: IpAddr) -> Self {
+		let prefix_len = match address {
+			IpAddr::V4(_) => 32,
+			IpAddr::V6(_) => 128
+		};
+		Self {
+			address,
+			prefix_len
+		}
+	}
+}
+
+impl Display for IpSubnet {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}/{}", self.address, self.prefix_len)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::IpAddr;
+	use crate::server::IpSubnet;
+
+	#[test]
+	fn contains() {
+		let subnet = IpSubnet::new("192.168.17.5".parse().unwrap(), 16).unwrap();
+		assert_eq!(subnet.get_address(), "192.168.0.0".parse::<IpAddr>().unwrap());
+		assert!(subnet.contains(&"192.168.255.1".parse().unwrap()));
+		assert!(!subnet.contains(&"192.169.0.1".parse().unwrap()));
+		assert!(!subnet.contains(&"::1".parse().unwrap()));
+
+		let subnet = IpSubnet::new("2001:db8::1".parse().unwrap(), 32).unwrap();
+		assert!(subnet.contains(&"2001:db8:ffff::".parse().unwrap()));
+		assert!(!subnet.contains(&"2001:db9::".parse().unwrap()));
+
+		assert!(IpSubnet::new("0.0.0.0".parse().unwrap(), 0).unwrap().contains(&"8.8.8.8".parse().unwrap()));
+		assert!(IpSubnet::new("0.0.0.0".parse().unwrap(), 33).is_none());
+	}
+}
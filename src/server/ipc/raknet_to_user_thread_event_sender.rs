@@ -2,7 +2,7 @@ use std::sync::{Arc, Mutex};
 use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::time::Duration;
-use crate::server::{ServerEventListener, ServerEvent};
+use crate::server::{ServerEventListener, ServerEvent, IpSubnet};
 
 pub struct RaknetToUserThreadEventSender {
 	channel: Arc<Mutex<VecDeque<ServerEvent>>>
@@ -64,10 +64,20 @@ impl ServerEventListener for RaknetToUserThreadEventSender {
 	}
 
 	#[inline]
-	fn on_bandwidth_stats_update(&mut self, bytes_sent_diff: usize, bytes_received_diff: usize) {
+	fn on_packet_loss(&mut self, session_id: usize, identifier_ack: u64) {
+		self.handle_event(ServerEvent::PacketLoss {
+			session_id,
+			identifier_ack
+		})
+	}
+
+	#[inline]
+	fn on_bandwidth_stats_update(&mut self, bytes_sent_diff: usize, bytes_received_diff: usize, dropped_packets: usize, deferred_packets: usize) {
 		self.handle_event(ServerEvent::BandwidthStatsUpdate {
 			bytes_sent_diff,
-			bytes_received_diff
+			bytes_received_diff,
+			dropped_packets,
+			deferred_packets
 		})
 	}
 
@@ -78,4 +88,19 @@ impl ServerEventListener for RaknetToUserThreadEventSender {
 			latency
 		})
 	}
-}
\ No newline at end of file
+
+	#[inline]
+	fn on_address_block(&mut self, subnet: IpSubnet, timeout: Duration) {
+		self.handle_event(ServerEvent::AddressBlock {
+			subnet,
+			timeout
+		})
+	}
+
+	#[inline]
+	fn on_address_unblock(&mut self, subnet: IpSubnet) {
+		self.handle_event(ServerEvent::AddressUnblock {
+			subnet
+		})
+	}
+}
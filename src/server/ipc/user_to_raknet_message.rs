@@ -2,6 +2,7 @@ use regex::bytes::Regex;
 use crate::protocol::EncapsulatedPacket;
 use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
+use crate::server::IpSubnet;
 
 pub enum UserToRaknetMessage {
 	Encapsulated {
@@ -21,8 +22,16 @@ pub enum UserToRaknetMessage {
 		timeout: Duration
 	},
 	UnblockAddress(IpAddr),
+	BlockSubnet {
+		subnet: IpSubnet,
+		timeout: Duration
+	},
+	UnblockSubnet(IpSubnet),
 	RawFilter(Regex),
 	SetName(String),
 	SetPortCheck(bool),
-	SetPacketsPerTickLimit(usize)
+	SetPacketsPerTickLimit(usize),
+	Shutdown {
+		timeout: Duration
+	}
 }
\ No newline at end of file
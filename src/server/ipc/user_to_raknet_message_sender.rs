@@ -7,6 +7,7 @@ use regex::bytes::Regex;
 use std::time::Duration;
 use crate::protocol::EncapsulatedPacket;
 use parking_lot::Mutex;
+use crate::server::IpSubnet;
 
 pub struct UserToRaknetMessageSender {
 	channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>>
@@ -78,10 +79,30 @@ impl ServerInterface for UserToRaknetMessageSender {
 		self.handle_message(UserToRaknetMessage::UnblockAddress(address.clone()));
 	}
 
+	#[inline]
+	fn block_subnet(&mut self, subnet: IpSubnet, timeout: Duration) {
+		self.handle_message(UserToRaknetMessage::BlockSubnet {
+			subnet,
+			timeout
+		});
+	}
+
+	#[inline]
+	fn unblock_subnet(&mut self, subnet: &IpSubnet) {
+		self.handle_message(UserToRaknetMessage::UnblockSubnet(*subnet));
+	}
+
 	#[inline]
 	fn add_raw_packet_filter(&mut self, regex: Regex) {
 		self.handle_message(UserToRaknetMessage::RawFilter(regex));
 	}
+
+	#[inline]
+	fn shutdown(&mut self, timeout: Duration) {
+		self.handle_message(UserToRaknetMessage::Shutdown {
+			timeout
+		});
+	}
 }
 
 impl UserToRaknetMessageSender {
@@ -1,16 +1,36 @@
 pub mod ipc;
 
+#[cfg(feature = "tokio")]
+mod async_server;
+mod cookie_jar;
+mod ip_subnet;
+mod packet_overflow_policy;
 mod protocol_acceptor;
 mod server;
+mod server_config;
+mod server_config_error;
 mod server_event;
 mod server_event_listener;
+#[cfg(feature = "tokio")]
+mod server_event_stream;
 mod server_interface;
+mod server_socket;
 mod session;
 mod unconnected_message_handler;
 
+#[cfg(feature = "tokio")]
+pub use async_server::AsyncServer;
+pub use cookie_jar::CookieJar;
+pub use ip_subnet::IpSubnet;
+pub use packet_overflow_policy::PacketOverflowPolicy;
 pub use protocol_acceptor::ProtocolAcceptor;
 pub use server::*;
+pub use server_config::{ServerConfig, ServerConfigBuilder};
+pub use server_config_error::ServerConfigError;
 pub use server_event::ServerEvent;
 pub use server_event_listener::ServerEventListener;
+#[cfg(feature = "tokio")]
+pub use server_event_stream::ServerEventStream;
 pub use server_interface::ServerInterface;
+pub use server_socket::ServerSocket;
 pub use session::*;
\ No newline at end of file
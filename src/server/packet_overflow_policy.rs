@@ -0,0 +1,10 @@
+/**
+ * What happens to datagrams received after the processing limit of the current tick was reached
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum PacketOverflowPolicy {
+	/// Processed during the following ticks, dropped if the queue is full
+	#[default]
+	Queue,
+	Drop
+}
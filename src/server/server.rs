@@ -3,8 +3,8 @@ use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 use regex::bytes::Regex;
 use crate::server::ipc::{UserToRaknetMessageReceiver, UserToRaknetMessage};
-use crate::server::{ServerEventListener, ProtocolAcceptor, Session, ServerInterface};
-use std::io::ErrorKind;
+use crate::server::{ServerEventListener, ProtocolAcceptor, Session, ServerInterface, ServerConfig, IpSubnet, PacketOverflowPolicy, ServerSocket, CookieJar};
+use std::io::{self, ErrorKind};
 use crate::protocol::{Datagram, EncodePacket, DecodeBody, NACK, ACK, EncapsulatedPacket, MessageIdentifiers, DecodePacket, PacketImpl, UnconnectedPong};
 use parking_lot::{Mutex};
 use crate::server::unconnected_message_handler::UnconnectedMessageHandler;
@@ -13,9 +13,10 @@ use crate::RaknetTime;
 use log::{debug, info};
 use std::sync::{Arc};
 use crate::server::session::SessionExport;
+use crate::generic::DatagramCipher;
 use std::convert::TryFrom;
 use blockingqueue::BlockingQueue;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::sleep;
 use std::fmt::Debug;
 
@@ -35,74 +36,107 @@ impl<'a> Deref for Server<'a> {
 
 impl<'a> Server<'a> {
 
-	const RAKLIB_TPS: u32 = 100;
-	const RAKLIB_TIME_PER_TICK: Duration = Duration::from_millis(10);
-
+	/**
+	 * A socket bound to `[::]` serves IPv4 and IPv6 clients on dual-stack systems,
+	 * IPv4 clients are identified by their plain IPv4 address.
+	 */
 	pub fn new(
 		server_id: u64,
 		udp_socket: UdpSocket,
-		max_mtu_size: usize,
+		config: ServerConfig,
 		protocol_acceptor: impl ProtocolAcceptor + 'a,
 		event_source: UserToRaknetMessageReceiver,
 		event_listener: impl ServerEventListener + 'a,
-	) -> Self {
+	) -> io::Result<Self> {
+		Self::with_sockets(server_id, vec![udp_socket], config, protocol_acceptor, event_source, event_listener)
+	}
+
+	/**
+	 * Serves every socket with one session table, sessions reply through the socket they connected on.
+	 * The first socket is the primary one, see `ServerExport::get_port`. Fails if no socket is given.
+	 */
+	pub fn with_sockets(
+		server_id: u64,
+		udp_sockets: Vec<UdpSocket>,
+		config: ServerConfig,
+		protocol_acceptor: impl ProtocolAcceptor + 'a,
+		event_source: UserToRaknetMessageReceiver,
+		event_listener: impl ServerEventListener + 'a,
+	) -> io::Result<Self> {
+		if udp_sockets.is_empty() {
+			return Err(io::Error::new(ErrorKind::InvalidInput, "server needs at least one socket"));
+		}
 		let immutable = Arc::new(ServerExport::new(
 			server_id,
-			udp_socket,
-			max_mtu_size,
+			udp_sockets.into_iter().map(ServerSocket::new).collect::<io::Result<_>>()?,
+			config,
 			protocol_acceptor,
 			event_listener,
 			event_source
 		));
-		Self {
+		Ok(Self {
 			internal: Mutex::new(ServerInternal::new(
 				immutable.clone()
 			)),
 			export: immutable
-		}
+		})
 	}
 
-	pub fn run(&self) {
-		self.udp_socket.set_nonblocking(false);
-		let stop = Arc::new(Mutex::new(()));
-		let stop_c = stop.clone();
-		let thread = unsafe { std::thread::Builder::new().spawn_unchecked(move || while !stop_c.is_locked() {
-			self.receive_packet();
-		}).unwrap() };
-		while {
-			true // TODO !self.mutable.lock().shutdown && self.mutable.lock().sessions.len() > 0
-		} {
-			for _ in 0..Self::RAKLIB_TPS {
+	/**
+	 * Runs until the server is shut down and every session is gone.
+	 * Fails if the sockets can't be switched to blocking mode.
+	 */
+	pub fn run(&self) -> io::Result<()> {
+		for socket in &self.sockets {
+			socket.udp_socket.set_nonblocking(false)?;
+			//wake up the receive threads periodically so they can notice the shutdown
+			socket.udp_socket.set_read_timeout(Some(self.config.tick_interval()))?;
+		}
+		let stop = AtomicBool::new(false);
+		std::thread::scope(| scope | {
+			let threads: Vec<_> = (0..self.sockets.len()).map(| socket_id | {
+				let stop = &stop;
+				scope.spawn(move || {
+					let mut buffer = vec![0; self.config.max_mtu_size()];
+					while !stop.load(Ordering::Relaxed) {
+						self.receive_packet(socket_id, &mut buffer);
+					}
+				})
+			}).collect();
+			while !self.internal.lock().is_shut_down() {
 				self.tick_processor();
 			}
-		}
-		let lock = stop.lock();
-		self.udp_socket.set_nonblocking(true);
-		thread.join().unwrap()
+			stop.store(true, Ordering::Relaxed);
+			for thread in threads {
+				thread.join().unwrap()
+			}
+		});
+		Ok(())
 	}
 
 	fn tick_processor(&self) {
 		let start = Instant::now();
 		{
 			let mut mutable = self.internal.lock();
-			while let Some(message) = self.event_source.receive() {
-				if mutable.shutdown {
-					break;
+			//messages are left queued once shutting down
+			while !mutable.shutdown {
+				match self.event_source.receive() {
+					Some(message) => mutable.handle_message(message),
+					None => break
 				}
-				mutable.handle_message(message)
 			}
 
 			mutable.tick()
 		}
 		let elapsed = start.elapsed();
-		if elapsed < Self::RAKLIB_TIME_PER_TICK {
-			sleep(Self::RAKLIB_TIME_PER_TICK - elapsed);
+		let tick_interval = self.config.tick_interval();
+		if elapsed < tick_interval {
+			sleep(tick_interval - elapsed);
 		}
 	}
 
-	fn receive_packet(&self) -> bool {
-		let mut buffer = self.buffer.lock();
-		return match self.udp_socket.recv_from(&mut buffer) {
+	fn receive_packet(&self, socket_id: usize, buffer: &mut [u8]) -> bool {
+		return match self.sockets[socket_id].udp_socket.recv_from(buffer) {
 			Err(e) => {
 				match e.kind() {
 					ErrorKind::ConnectionReset => true,
@@ -114,7 +148,7 @@ impl<'a> Server<'a> {
 				}
 			}
 			Ok((read, address)) => {
-				self.internal.lock().receive_packet(address, &buffer.as_slice()[..read]);
+				self.internal.lock().receive_packet(socket_id, address, &buffer[..read]);
 				true
 			}
 		}
@@ -127,23 +161,35 @@ pub struct ServerInternal<'a> { // TODO move out values not being wrote while ru
 
 	receive_bytes: usize,
 
+	tick_datagrams: usize,
+	session_datagrams: HashMap<usize, usize>,
+	deferred: VecDeque<(usize, SocketAddr, Vec<u8>)>,
+	dropped_packets: usize,
+	deferred_packets: usize,
+
 	sessions: Vec<Option<Session<'a>>>,
 	session_ids_by_address: HashMap<SocketAddr, usize/* index in sessions */>,
+	session_counts_by_ip: HashMap<IpAddr, usize>,
 
 	pub name: String,
 
-	pub packet_per_tick_limit: usize, //default 200
+	pub packet_per_tick_limit: usize,
+
+	pub(super) shutdown: bool,
 
-	shutdown: bool,
+	shutdown_deadline: Option<Instant>,
 
 	ticks: u32, //default 0
 	
 	block: HashMap<IpAddr, Instant>,
+	subnet_block: HashMap<IpSubnet, Instant>,
 	ip_sec: HashMap<IpAddr, usize>,
+	rate_limit_blocks: HashMap<IpAddr, usize>,
+	recent_connections: HashMap<IpAddr, Instant>, //only tracked if ip_reconnect_interval is set
 
 	raw_packet_filters: Vec<Regex>,
 
-	reusable_address: SocketAddr,
+	pub(super) cookie_jar: Option<CookieJar>,
 
 	reusable_session_ids: VecDeque<usize>,
 
@@ -162,30 +208,105 @@ impl<'a> ServerInternal<'a> {
 	fn new(
 		immutable: Arc<ServerExport<'a>>
 	) -> Self {
-		let reusable_address = immutable.udp_socket.local_addr().unwrap();
-		let max_mtu_size = immutable.max_mtu_size;
+		let packet_per_tick_limit = immutable.config.packet_per_tick_limit();
+		//the handshake challenge of security is only read along a cookie
+		let cookie_jar = if immutable.config.use_cookies() || immutable.config.has_security() {
+			Some(CookieJar::new(immutable.config.cookie_rotation_interval()))
+		} else {
+			None
+		};
 		Self {
 			export: immutable,
 			receive_bytes: 0,
+			tick_datagrams: 0,
+			session_datagrams: HashMap::new(),
+			deferred: VecDeque::new(),
+			dropped_packets: 0,
+			deferred_packets: 0,
 			session_ids_by_address: HashMap::new(),
+			session_counts_by_ip: HashMap::new(),
 			sessions: Vec::new(),
 			name: "".to_string(),
-			packet_per_tick_limit: 200,
+			packet_per_tick_limit,
 			shutdown: false,
+			shutdown_deadline: None,
 			ticks: 0,
 			block: HashMap::new(),
+			subnet_block: HashMap::new(),
 			ip_sec: HashMap::new(),
+			rate_limit_blocks: HashMap::new(),
+			recent_connections: HashMap::new(),
 			raw_packet_filters: Vec::new(),
-			reusable_address,
+			cookie_jar,
 			reusable_session_ids: VecDeque::new(),
 		}
 	}
 
 
-	fn receive_packet(&mut self, address: SocketAddr, mut buffer: &[u8]) {
-		if buffer.len() == 0 {
+	pub(super) fn receive_packet(&mut self, socket_id: usize, address: SocketAddr, buffer: &[u8]) {
+		let address = to_canonical(address);
+		self.sockets[socket_id].on_receive(buffer.len());
+		if buffer.len() == 0 || self.is_blocked(&address.ip()) {
+			return;
+		}
+		let packets = self.ip_sec.entry(address.ip()).or_insert(0);
+		*packets += 1;
+		if *packets > self.packet_per_tick_limit {
+			info!("Blocking {} for exceeding the limit of {} packets per tick", address.ip(), self.packet_per_tick_limit);
+			*self.rate_limit_blocks.entry(address.ip()).or_insert(0) += 1;
+			self.block_address(address.ip(), self.config.rate_limit_block_duration());
 			return;
 		}
+		self.receive_bytes += buffer.len();
+
+		if !self.reserve_processing(&address) {
+			match self.config.packet_overflow_policy() {
+				PacketOverflowPolicy::Queue if self.deferred.len() < self.config.max_datagrams_per_tick() => {
+					self.deferred.push_back((socket_id, address, buffer.to_vec()));
+					self.deferred_packets += 1;
+				},
+				_ => self.dropped_packets += 1
+			}
+			return;
+		}
+		self.handle_packet(socket_id, address, buffer);
+	}
+
+	/**
+	 * Returns false if the datagram exceeds the processing limits of the current tick
+	 */
+	fn reserve_processing(&mut self, address: &SocketAddr) -> bool {
+		if self.tick_datagrams >= self.config.max_datagrams_per_tick() {
+			return false;
+		}
+		if let Some(session_id) = self.session_ids_by_address.get(address) {
+			let limit = self.export.config.max_session_datagrams_per_tick();
+			let datagrams = self.session_datagrams.entry(*session_id).or_insert(0);
+			if *datagrams >= limit {
+				return false;
+			}
+			*datagrams += 1;
+		}
+		self.tick_datagrams += 1;
+		true
+	}
+
+	fn process_deferred(&mut self) {
+		self.tick_datagrams = 0;
+		self.session_datagrams.clear();
+		for (socket_id, address, buffer) in std::mem::take(&mut self.deferred) {
+			if self.is_blocked(&address.ip()) {
+				continue;
+			}
+			if self.reserve_processing(&address) {
+				self.handle_packet(socket_id, address, &buffer);
+			} else {
+				self.deferred.push_back((socket_id, address, buffer));
+			}
+		}
+	}
+
+	fn handle_packet(&mut self, socket_id: usize, address: SocketAddr, buffer: &[u8]) {
 		debug!("recv: {} from {}", if let Ok(id) = MessageIdentifiers::try_from(buffer[0]) {
 			format!("{:?}", id)
 		} else {
@@ -194,14 +315,35 @@ impl<'a> ServerInternal<'a> {
 		match self.session_ids_by_address.get(&address).cloned() {
 			Some(session_id) => {
 				let session = self.sessions[session_id].as_ref().unwrap();
+				if session.socket_id != socket_id {
+					debug!("Ignored packet from {} on socket {} due to session opened on socket {}", address, socket_id, session.socket_id);
+					return;
+				}
+				let decrypted;
+				let mut buffer = buffer;
+				if let Some(cipher) = &session.cipher {
+					match cipher.decrypt(buffer) {
+						Some(payload) if !payload.is_empty() => {
+							decrypted = payload;
+							buffer = &decrypted;
+						},
+						_ => {
+							debug!("Dropped packet from {} which failed to decrypt", address);
+							return;
+						}
+					}
+				}
 				let header = buffer[0];
 				if (header & Datagram::FLAG_VALID) != 0 {
-					if (header & Datagram::FLAG_ACK) != 0 {
-						session.get_mut().handle_ack(ACK::decode_packet(&mut buffer));
+					let result = if (header & Datagram::FLAG_ACK) != 0 {
+						ACK::decode_packet(&mut buffer).map(| packet | session.get_mut().handle_ack(packet))
 					} else if (header & Datagram::FLAG_NAK) != 0 {
-						session.get_mut().handle_nack(NACK::decode_packet(&mut buffer));
+						NACK::decode_packet(&mut buffer).map(| packet | session.get_mut().handle_nack(packet))
 					} else {
-						session.get_mut().handle_datagram(Datagram::decode_packet(&mut buffer));
+						Datagram::decode_packet(&mut buffer).map(| packet | session.get_mut().handle_datagram(packet))
+					};
+					if let Err(e) = result {
+						debug!("Dropped bad packet from {}: {}", address, e);
 					}
 				} else {
 					debug!("Ignored unconnected packet from {} due to session already opened ({})", address, if let Ok(id) = MessageIdentifiers::try_from(header) {
@@ -212,7 +354,7 @@ impl<'a> ServerInternal<'a> {
 				}
 			},
 			None => if !self.shutdown {
-				let mut handled = self.handle_raw(&address, buffer);
+				let mut handled = self.handle_raw(socket_id, &address, buffer);
 				if !handled {
 					for x in &self.raw_packet_filters {
 						if x.find(&buffer).is_some() {
@@ -225,7 +367,8 @@ impl<'a> ServerInternal<'a> {
 
 				if !handled {
 					debug!(
-						"Ignored packet from $address due to no session opened ({})",
+						"Ignored packet from {} due to no session opened ({})",
+						address,
 						if let Ok(id) = MessageIdentifiers::try_from(buffer[0]) {
 							format!("{:#04x}, {:?}", buffer[0], id)
 						} else {
@@ -235,29 +378,82 @@ impl<'a> ServerInternal<'a> {
 				}
 			}
 		}
-		//TODO catch bad packet
+	}
+
+	pub fn is_blocked(&self, address: &IpAddr) -> bool {
+		self.block.contains_key(address) || self.subnet_block.keys().any(| subnet | subnet.contains(address))
+	}
+
+	/**
+	 * Packets received from each address during the current tick
+	 */
+	pub fn get_packet_counts(&self) -> &HashMap<IpAddr, usize> {
+		&self.ip_sec
+	}
+
+	/**
+	 * How often each address was blocked for exceeding the packet limit
+	 */
+	pub fn get_rate_limit_blocks(&self) -> &HashMap<IpAddr, usize> {
+		&self.rate_limit_blocks
+	}
+
+	fn disconnect_subnet(&mut self, subnet: &IpSubnet) {
+		for session in self.sessions.iter().flatten() {
+			if subnet.contains(&session.address.ip()) {
+				session.forcibly_disconnect("address blocked");
+			}
+		}
 	}
 
 	pub fn get_session_by_address(&self, address: &SocketAddr) -> Option<&Session<'a>> {
 		self.session_ids_by_address.get(address).map(| x | self.sessions.get(*x).unwrap().as_ref().unwrap())
 	}
 
-	pub fn create_session(&mut self, address: SocketAddr, client_id: u64, mtu_size: usize) {
+	/**
+	 * Sessions which are open, including those still connecting or disconnecting
+	 */
+	pub fn get_session_count(&self) -> usize {
+		self.sessions.len() - self.reusable_session_ids.len()
+	}
+
+	pub fn get_session_count_by_ip(&self, ip: &IpAddr) -> usize {
+		self.session_counts_by_ip.get(ip).copied().unwrap_or(0)
+	}
+
+	/**
+	 * Whether the IP opened a session within `ip_reconnect_interval`
+	 */
+	pub fn is_recently_connected(&self, ip: &IpAddr) -> bool {
+		self.recent_connections.get(ip).is_some_and(| time | time.elapsed() < self.config.ip_reconnect_interval())
+	}
+
+	pub fn create_session(&mut self, socket_id: usize, address: SocketAddr, client_id: u64, mtu_size: usize, cipher: Option<Arc<dyn DatagramCipher>>) {
 		self.check_sessions();
+		if !self.config.ip_reconnect_interval().is_zero() {
+			self.recent_connections.insert(address.ip(), Instant::now());
+		}
 		let session_id = self.reusable_session_ids.pop_front().unwrap_or_else(|| {
 			let id = self.sessions.len();
 			self.sessions.push(None);
 			id
 		});
 
-		self.sessions[session_id] = Some(Session::new(self.export.clone(), address.clone(), client_id, mtu_size, session_id));
+		self.sessions[session_id] = Some(Session::new(self.export.clone(), socket_id, address.clone(), client_id, mtu_size, session_id, cipher));
 		self.session_ids_by_address.insert(address, session_id);
-		debug!("Created session for {} with MTU size {}", address, mtu_size);
+		*self.session_counts_by_ip.entry(address.ip()).or_insert(0) += 1;
+		debug!("Created session for {} on socket {} with MTU size {}", address, socket_id, mtu_size);
 	}
 
 	fn remove_session_internal(&mut self, session_id: usize) {
-		let session = self.sessions.remove(session_id).unwrap();
+		let session = self.sessions[session_id].take().unwrap();
 		self.session_ids_by_address.remove(&session.address);
+		if let Some(count) = self.session_counts_by_ip.get_mut(&session.address.ip()) {
+			*count -= 1;
+			if *count == 0 {
+				self.session_counts_by_ip.remove(&session.address.ip());
+			}
+		}
 		self.reusable_session_ids.push_back(session_id);
 	}
 
@@ -265,7 +461,7 @@ impl<'a> ServerInternal<'a> {
 	fn check_sessions(&mut self) {
 		let mut to_remove = Vec::new();
 
-		if self.sessions.len() - self.reusable_session_ids.len() > 4096 {
+		if self.get_session_count() > self.config.session_gc_threshold() {
 			for x in &self.sessions {
 				if let Some(session) = x {
 					if session.get_mut().is_temporal() {
@@ -280,13 +476,20 @@ impl<'a> ServerInternal<'a> {
 		}
 	}
 
-	fn tick(&mut self) {
+	pub(super) fn tick(&mut self) {
 		let time = Instant::now();
 
+		self.process_deferred();
+
+		let shutdown_expired = self.shutdown_deadline.is_some_and(| deadline | time >= deadline);
+
 		let mut to_remove = Vec::new();
 		for x in &mut self.sessions {
 			if let Some(session) = x {
 				let mut session = session.get_mut();
+				if shutdown_expired && !session.is_fully_disconnected() {
+					session.forcibly_disconnect("server shutdown timeout");
+				}
 				session.update(time);
 				if session.is_fully_disconnected() {
 					to_remove.push(session.internal_id);
@@ -299,14 +502,16 @@ impl<'a> ServerInternal<'a> {
 
 		self.ip_sec.clear();
 
-		if !self.shutdown && self.ticks == Server::RAKLIB_TPS {
+		if self.ticks == self.config.tick_rate() {
 			self.ticks = 0;
 			{
 				let mut send_bytes = self.export.send_bytes.lock();
-				if *send_bytes > 0 || self.receive_bytes > 0 {
-					self.export.event_listener.lock().on_bandwidth_stats_update(*send_bytes, self.receive_bytes);
+				if *send_bytes > 0 || self.receive_bytes > 0 || self.dropped_packets > 0 || self.deferred_packets > 0 {
+					self.export.event_listener.lock().on_bandwidth_stats_update(*send_bytes, self.receive_bytes, self.dropped_packets, self.deferred_packets);
 					*send_bytes = 0;
 					self.receive_bytes = 0;
+					self.dropped_packets = 0;
+					self.deferred_packets = 0;
 				}
 			}
 
@@ -322,10 +527,35 @@ impl<'a> ServerInternal<'a> {
 					self.unblock_address(&x);
 				}
 			}
+
+			if !self.recent_connections.is_empty() {
+				let ip_reconnect_interval = self.config.ip_reconnect_interval();
+				self.recent_connections.retain(| _, time | time.elapsed() < ip_reconnect_interval);
+			}
+
+			if !self.subnet_block.is_empty() {
+				let now = Instant::now();
+				let mut to_remove = Vec::new();
+				for (subnet, instant) in &self.subnet_block {
+					if *instant < now {
+						to_remove.push(*subnet);
+					}
+				}
+				for x in to_remove {
+					self.unblock_subnet(&x);
+				}
+			}
 		}
 		self.ticks += 1;
 	}
 
+	/**
+	 * Returns whether shutdown was requested and every session has been destroyed
+	 */
+	pub fn is_shut_down(&self) -> bool {
+		self.shutdown && self.sessions.iter().all(Option::is_none)
+	}
+
 }
 
 impl ServerInterface for ServerInternal<'_> {
@@ -336,7 +566,18 @@ impl ServerInterface for ServerInternal<'_> {
 	}
 
 	fn send_raw(&mut self, address: &SocketAddr, payload: &[u8]) {
-		if let Err(e) = self.udp_socket.send_to(payload, address) {
+		//through the socket of the address's session, if there is one
+		let socket_id = match self.get_session_by_address(address) {
+			Some(session) => session.socket_id,
+			None => match self.sockets.iter().position(| socket | socket.can_reach(address)) {
+				Some(socket_id) => socket_id,
+				None => {
+					debug!("No socket can reach {}", address);
+					return;
+				}
+			}
+		};
+		if let Err(e) = self.sockets[socket_id].send_to(payload, address) {
 			debug!("{:?}", e);
 		}
 	}
@@ -365,30 +606,62 @@ impl ServerInterface for ServerInternal<'_> {
 			info!("Blocked {} for {:?}", address, timeout);
 		}
 		self.block.insert(address, fin);
+		let subnet = IpSubnet::from(address);
+		self.disconnect_subnet(&subnet);
+		self.export.event_listener.lock().on_address_block(subnet, timeout);
 	}
 
 	fn unblock_address(&mut self, address: &IpAddr) {
-		self.block.remove(address);
-		debug!("Unblocked {}", address);
+		if self.block.remove(address).is_some() {
+			debug!("Unblocked {}", address);
+			self.export.event_listener.lock().on_address_unblock(IpSubnet::from(*address));
+		}
+	}
+
+	fn block_subnet(&mut self, subnet: IpSubnet, timeout: Duration) {
+		let fin = Instant::now() + timeout;
+		if self.subnet_block.get_mut(&subnet).is_none() {
+			info!("Blocked {} for {:?}", subnet, timeout);
+		}
+		self.subnet_block.insert(subnet, fin);
+		self.disconnect_subnet(&subnet);
+		self.export.event_listener.lock().on_address_block(subnet, timeout);
+	}
+
+	fn unblock_subnet(&mut self, subnet: &IpSubnet) {
+		if self.subnet_block.remove(subnet).is_some() {
+			debug!("Unblocked {}", subnet);
+			self.export.event_listener.lock().on_address_unblock(*subnet);
+		}
 	}
 
 	fn add_raw_packet_filter(&mut self, regex: Regex) {
 		self.raw_packet_filters.push(regex);
 	}
+
+	fn shutdown(&mut self, timeout: Duration) {
+		if self.shutdown {
+			return;
+		}
+		self.shutdown = true;
+		self.shutdown_deadline = Some(Instant::now() + timeout);
+		for session in self.sessions.iter().flatten() {
+			session.initiate_disconnect("server shutdown");
+		}
+		info!("Shutting down, waiting up to {:?} for sessions to disconnect", timeout);
+	}
 }
 
 pub struct ServerExport<'a> {
 
-	pub max_mtu_size: usize,
+	pub config: ServerConfig,
 
 	pub id: u64,
 
-	pub udp_socket: UdpSocket,
+	pub sockets: Vec<ServerSocket>,
 
 	pub send_bytes: Mutex<usize>,
 
-	buffer: Mutex<Vec<u8>>,
-
 	send_buffer: Mutex<Vec<u8>>,
 
 	pub start_time: Instant,
@@ -406,18 +679,17 @@ pub struct ServerExport<'a> {
 impl<'a> ServerExport<'a> {
 	pub fn new(
 		server_id: u64,
-		udp_socket: UdpSocket,
-		max_mtu_size: usize,
+		sockets: Vec<ServerSocket>,
+		config: ServerConfig,
 		protocol_acceptor: impl ProtocolAcceptor + 'a,
 		event_listener: impl ServerEventListener + 'a,
 		event_source: UserToRaknetMessageReceiver
 	) -> Self {
 		Self {
-			max_mtu_size,
+			config,
 			id: server_id,
-			udp_socket,
+			sockets,
 			send_bytes: Mutex::new(0),
-			buffer: Mutex::new(vec![0; max_mtu_size]),
 			send_buffer: Mutex::new(vec![]),
 			port_checking: Mutex::new(false),
 			start_time: Instant::now(),
@@ -439,7 +711,14 @@ impl<'a> ServerExport<'a> {
 		);
 	}
 
-	pub fn send_packet(&self, packet: &impl PacketImpl, address: &SocketAddr) {
+	pub fn send_packet(&self, packet: &impl PacketImpl, socket_id: usize, address: &SocketAddr) {
+		self.send_session_packet(packet, None, socket_id, address)
+	}
+
+	/**
+	 * Sends a packet of a session, encrypted if the session negotiated security
+	 */
+	pub fn send_session_packet(&self, packet: &impl PacketImpl, cipher: Option<&dyn DatagramCipher>, socket_id: usize, address: &SocketAddr) {
 		let mut buffer = self.send_buffer.lock();
 		buffer.clear();
 		packet.encode_packet(&mut *buffer);
@@ -448,14 +727,25 @@ impl<'a> ServerExport<'a> {
 		} else {
 			format!("{:#04x}", buffer[0])
 		}, address);
-		match self.udp_socket.send_to(&*buffer, address) {
+		let result = match cipher {
+			Some(cipher) => self.sockets[socket_id].send_to(&cipher.encrypt(&buffer), address),
+			None => self.sockets[socket_id].send_to(&buffer, address)
+		};
+		match result {
 			Ok(send) => *self.send_bytes.lock() += send,
 			Err(e) => debug!("{}", e)
 		}
 	}
 
+	/**
+	 * Port of the primary socket
+	 */
 	pub fn get_port(&self) -> u16 {
-		self.udp_socket.local_addr().unwrap().port()
+		self.sockets[0].get_port()
+	}
+
+	pub fn has_port(&self, port: u16) -> bool {
+		self.sockets.iter().any(| socket | socket.get_port() == port)
 	}
 
 	pub fn get_port_checking(&self) -> bool {
@@ -465,4 +755,465 @@ impl<'a> ServerExport<'a> {
 	pub fn set_port_checking(&self, port_checking: bool) {
 		*self.port_checking.lock() = port_checking;
 	}
-}
\ No newline at end of file
+}
+/**
+ * IPv4 clients of a dual-stack socket arrive with IPv4-mapped IPv6 addresses, they are keyed by their IPv4 address
+ */
+fn to_canonical(address: SocketAddr) -> SocketAddr {
+	match address {
+		SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+			Some(ip) => SocketAddr::from((ip, v6.port())),
+			None => address
+		},
+		_ => address
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+	use std::net::{SocketAddr, UdpSocket, IpAddr};
+	use std::sync::Arc;
+	use std::thread::{spawn, sleep, JoinHandle};
+	use std::time::{Duration, Instant};
+	use parking_lot::Mutex;
+	use crate::client::{Client, ClientEventListener};
+	use crate::server::{Server, ServerEventListener, ProtocolAcceptor, ServerInterface, ServerConfig, IpSubnet, ServerEvent, PacketOverflowPolicy};
+	use crate::server::ipc::{UserToRaknetMessage, UserToRaknetMessageReceiver, UserToRaknetMessageSender, RaknetToUserThreadEventSender};
+	use crate::protocol::{UnconnectedPing, OfflineMessage, EncodePacket, OpenConnectionRequest2, MessageIdentifiers};
+	use crate::DEFAULT_PROTOCOL_VERSION;
+
+	struct PA;
+
+	impl ProtocolAcceptor for PA {
+		fn accepts(&self, version: u8) -> bool {
+			version == DEFAULT_PROTOCOL_VERSION
+		}
+
+		fn get_primary_version(&self) -> u8 {
+			DEFAULT_PROTOCOL_VERSION
+		}
+	}
+
+	/// Shuts the server down once the given number of clients connected
+	struct ShutdownAfter(usize, UserToRaknetMessageSender);
+
+	impl ServerEventListener for ShutdownAfter {
+		fn on_client_connect(&mut self, _session_id: usize, _address: SocketAddr, _client_id: u64) {
+			self.0 -= 1;
+			if self.0 == 0 {
+				self.1.shutdown(Duration::from_secs(5));
+			}
+		}
+
+		fn on_client_disconnect(&mut self, _session_id: usize, _reason: &str) {}
+
+		fn on_packet_receive(&mut self, _session_id: usize, _packet: &[u8]) {}
+
+		fn on_raw_packet_receive(&mut self, _address: SocketAddr, _payload: &[u8]) {}
+
+		fn on_packet_ack(&mut self, _session_id: usize, _identifier_ack: u64) {}
+
+		fn on_packet_loss(&mut self, _session_id: usize, _identifier_ack: u64) {}
+
+		fn on_bandwidth_stats_update(&mut self, _bytes_sent_diff: usize, _bytes_received_diff: usize, _dropped_packets: usize, _deferred_packets: usize) {}
+
+		fn on_ping_measure(&mut self, _session_id: usize, _latency: Duration) {}
+
+		fn on_address_block(&mut self, _subnet: IpSubnet, _timeout: Duration) {}
+
+		fn on_address_unblock(&mut self, _subnet: IpSubnet) {}
+	}
+
+	struct CL(Arc<Mutex<Option<String>>>);
+
+	impl ClientEventListener for CL {
+		fn on_connect(&mut self, _server_address: SocketAddr, _server_id: u64) {}
+
+		fn on_disconnect(&mut self, reason: &str) {
+			*self.0.lock() = Some(reason.to_owned());
+		}
+
+		fn on_packet_receive(&mut self, _packet: &[u8]) {}
+
+		fn on_packet_ack(&mut self, _identifier_ack: u64) {}
+
+		fn on_packet_loss(&mut self, _identifier_ack: u64) {}
+
+		fn on_ping_measure(&mut self, _latency: Duration) {}
+	}
+
+	/// Connects a client on another thread, it records the reason it was disconnected with
+	fn spawn_client(server_address: SocketAddr) -> (JoinHandle<()>, Arc<Mutex<Option<String>>>) {
+		let disconnect_reason: Arc<Mutex<Option<String>>> = Default::default();
+		let listener = CL(disconnect_reason.clone());
+		(spawn(move || {
+			let client = Client::connect(server_address, 1, DEFAULT_PROTOCOL_VERSION, listener).unwrap();
+			client.run();
+		}), disconnect_reason)
+	}
+
+	/// Waits for a client of `spawn_client` to be disconnected by the server's shutdown
+	fn join_client((client, disconnect_reason): (JoinHandle<()>, Arc<Mutex<Option<String>>>)) {
+		client.join().unwrap();
+		assert_eq!(disconnect_reason.lock().as_deref(), Some("server disconnect"));
+	}
+
+	fn create_server<'a>(listener: impl ServerEventListener + 'a, channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>>) -> Server<'a> {
+		Server::new(
+			0,
+			UdpSocket::bind("127.0.0.1:0").unwrap(),
+			ServerConfig::default(),
+			PA,
+			UserToRaknetMessageReceiver::new(channel),
+			listener
+		).unwrap()
+	}
+
+	#[test]
+	fn shutdown_without_sessions() {
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let server = create_server(ShutdownAfter(1, UserToRaknetMessageSender::new(channel.clone())), channel.clone());
+		UserToRaknetMessageSender::new(channel).shutdown(Duration::from_secs(5));
+		let start = Instant::now();
+		server.run().unwrap();
+		assert!(start.elapsed() < Duration::from_secs(1));
+	}
+
+	#[test]
+	fn shutdown_disconnects_sessions() {
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let server = create_server(ShutdownAfter(1, UserToRaknetMessageSender::new(channel.clone())), channel);
+		let client = spawn_client(server.sockets[0].get_local_address());
+
+		let start = Instant::now();
+		server.run().unwrap();
+		assert!(start.elapsed() < Duration::from_secs(5));
+		assert!(server.internal.lock().is_shut_down());
+		join_client(client);
+	}
+
+	#[test]
+	fn dual_stack() {
+		for client_address in ["127.0.0.1", "::1"] {
+			let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+			let server = Server::new(
+				0,
+				UdpSocket::bind("[::]:0").unwrap(),
+				ServerConfig::default(),
+				PA,
+				UserToRaknetMessageReceiver::new(channel.clone()),
+				ShutdownAfter(1, UserToRaknetMessageSender::new(channel))
+			).unwrap();
+			let client = spawn_client(SocketAddr::new(client_address.parse().unwrap(), server.get_port()));
+			server.run().unwrap();
+			join_client(client);
+		}
+
+		//IPv4 clients are keyed by their IPv4 address
+		let server = create_server(RaknetToUserThreadEventSender::new(Default::default()), Default::default());
+		server.internal.lock().receive_packet(0, "[::ffff:10.0.0.1]:1".parse().unwrap(), &[0xff]);
+		assert!(server.internal.lock().get_packet_counts().contains_key(&"10.0.0.1".parse::<IpAddr>().unwrap()));
+	}
+
+	#[test]
+	fn multiple_sockets() {
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let server = Server::with_sockets(
+			0,
+			vec![UdpSocket::bind("127.0.0.1:0").unwrap(), UdpSocket::bind("127.0.0.1:0").unwrap()],
+			ServerConfig::default(),
+			PA,
+			UserToRaknetMessageReceiver::new(channel.clone()),
+			ShutdownAfter(2, UserToRaknetMessageSender::new(channel))
+		).unwrap();
+		server.set_port_checking(true);
+
+		//a client only accepts replies from the address it connected to
+		let clients: Vec<_> = server.sockets.iter().map(| socket | spawn_client(socket.get_local_address())).collect();
+
+		server.run().unwrap();
+		clients.into_iter().for_each(join_client);
+		for socket in &server.sockets {
+			assert!(socket.get_receive_datagrams() > 0);
+			assert!(socket.get_send_bytes() > 0);
+		}
+	}
+
+	#[test]
+	fn no_sockets() {
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let result = Server::with_sockets(
+			0,
+			vec![],
+			ServerConfig::default(),
+			PA,
+			UserToRaknetMessageReceiver::new(channel.clone()),
+			ShutdownAfter(1, UserToRaknetMessageSender::new(channel))
+		);
+		assert_eq!(result.err().map(| e | e.kind()), Some(std::io::ErrorKind::InvalidInput));
+	}
+
+	#[test]
+	fn cookies() {
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let server = Server::new(
+			0,
+			UdpSocket::bind("127.0.0.1:0").unwrap(),
+			ServerConfig::builder().use_cookies(true).build().unwrap(),
+			PA,
+			UserToRaknetMessageReceiver::new(channel.clone()),
+			ShutdownAfter(1, UserToRaknetMessageSender::new(channel))
+		).unwrap();
+
+		//a spoofed request without a valid cookie doesn't allocate a session
+		let spoofed: SocketAddr = "10.0.0.1:19132".parse().unwrap();
+		for cookie in [None, Some(0)] {
+			let mut buffer = Vec::new();
+			OpenConnectionRequest2 {
+				offline_message: Default::default(),
+				cookie,
+				challenge: None,
+				client_id: 1,
+				server_address: server.sockets[0].get_local_address(),
+				mtu_size: 1400
+			}.encode_packet(&mut buffer);
+			server.internal.lock().receive_packet(0, spoofed, &buffer);
+			assert!(server.internal.lock().get_session_by_address(&spoofed).is_none());
+		}
+
+		let client = spawn_client(server.sockets[0].get_local_address());
+		server.run().unwrap();
+		join_client(client);
+	}
+
+	#[cfg(feature = "security")]
+	#[test]
+	fn security() {
+		use crate::client::ConnectError;
+		use crate::generic::KeyPair;
+		use crate::protocol::{RemoteSystemRequiresPubKey, DecodePacket};
+
+		let key_pair = KeyPair::generate();
+		let public_key = key_pair.get_public_key();
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let server = Server::new(
+			0,
+			UdpSocket::bind("127.0.0.1:0").unwrap(),
+			ServerConfig::builder().key_pair(key_pair).build().unwrap(),
+			PA,
+			UserToRaknetMessageReceiver::new(channel.clone()),
+			ShutdownAfter(1, UserToRaknetMessageSender::new(channel))
+		).unwrap();
+		let server_address = server.sockets[0].get_local_address();
+
+		//clients have to send the handshake challenge
+		let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		let client_address = client.local_addr().unwrap();
+		let cookie = server.internal.lock().cookie_jar.as_mut().unwrap().generate(&client_address);
+		let mut buffer = Vec::new();
+		OpenConnectionRequest2 {
+			offline_message: Default::default(),
+			cookie: Some(cookie),
+			challenge: None,
+			client_id: 1,
+			server_address,
+			mtu_size: 1400
+		}.encode_packet(&mut buffer);
+		server.internal.lock().receive_packet(0, client_address, &buffer);
+		let mut reply = [0; 1500];
+		let read = client.recv(&mut reply).unwrap();
+		assert_eq!(RemoteSystemRequiresPubKey::decode_packet(&mut &reply[..read]).unwrap().server_id, 0);
+		assert!(server.internal.lock().get_session_by_address(&client_address).is_none());
+
+		let disconnect_reason: Arc<Mutex<Option<String>>> = Default::default();
+		let listener = CL(disconnect_reason.clone());
+		let client = spawn(move || {
+			let result = Client::connect_secure(server_address, 1, DEFAULT_PROTOCOL_VERSION, KeyPair::generate().get_public_key(), CL(Default::default()));
+			assert!(matches!(result, Err(ConnectError::PubKeyMismatch)));
+			let client = Client::connect_secure(server_address, 1, DEFAULT_PROTOCOL_VERSION, public_key, listener).unwrap();
+			client.run();
+		});
+		server.run().unwrap();
+		join_client((client, disconnect_reason));
+	}
+
+	/// Sends `OpenConnectionRequest2` from `client` and returns the id of the reply
+	fn request2(server: &Server, client: &UdpSocket) -> u8 {
+		let mut buffer = Vec::new();
+		OpenConnectionRequest2 {
+			offline_message: Default::default(),
+			cookie: None,
+			challenge: None,
+			client_id: 1,
+			server_address: server.sockets[0].get_local_address(),
+			mtu_size: 1400
+		}.encode_packet(&mut buffer);
+		server.internal.lock().receive_packet(0, client.local_addr().unwrap(), &buffer);
+		let mut reply = [0; 1500];
+		client.recv(&mut reply).unwrap();
+		reply[0]
+	}
+
+	#[test]
+	fn connection_limits() {
+		let config = ServerConfig::builder()
+			.max_connections(2)
+			.max_connections_per_ip(1)
+			.ip_reconnect_interval(Duration::from_secs(60))
+			.build()
+			.unwrap();
+		let server = Server::new(
+			0,
+			UdpSocket::bind("127.0.0.1:0").unwrap(),
+			config,
+			PA,
+			UserToRaknetMessageReceiver::new(Default::default()),
+			RaknetToUserThreadEventSender::new(Default::default())
+		).unwrap();
+		let clients: Vec<_> = ["127.0.0.1:0", "127.0.0.1:0", "127.0.0.2:0", "127.0.0.3:0"].iter().map(| address | {
+			let client = UdpSocket::bind(address).unwrap();
+			client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+			client
+		}).collect();
+
+		assert_eq!(request2(&server, &clients[0]), MessageIdentifiers::OpenConnectionReply2 as u8);
+		assert_eq!(request2(&server, &clients[1]), MessageIdentifiers::ConnectionBanned as u8);
+		assert_eq!(request2(&server, &clients[2]), MessageIdentifiers::OpenConnectionReply2 as u8);
+		assert_eq!(request2(&server, &clients[3]), MessageIdentifiers::NoFreeIncomingConnections as u8);
+		assert_eq!(server.internal.lock().get_session_count(), 2);
+
+		//the IP is throttled after its session closed
+		let session_id = server.internal.lock().get_session_by_address(&clients[0].local_addr().unwrap()).unwrap().internal_id;
+		let ip = clients[0].local_addr().unwrap().ip();
+		assert_eq!(server.internal.lock().get_session_count_by_ip(&ip), 1);
+		server.internal.lock().remove_session_internal(session_id);
+		assert_eq!(server.internal.lock().get_session_count_by_ip(&ip), 0);
+		assert_eq!(request2(&server, &clients[1]), MessageIdentifiers::IpRecentlyConnected as u8);
+		assert_eq!(request2(&server, &clients[3]), MessageIdentifiers::OpenConnectionReply2 as u8);
+	}
+
+	/// Returns whether the server answered an unconnected ping from `client`
+	fn pong(server: &Server, client: &UdpSocket) -> bool {
+		let mut buffer = Vec::new();
+		UnconnectedPing {
+			offline_message: OfflineMessage::default(),
+			send_ping_time: Duration::from_millis(1),
+			client_id: 1
+		}.encode_packet(&mut buffer);
+		server.internal.lock().receive_packet(0, client.local_addr().unwrap(), &buffer);
+		client.recv(&mut [0; 1500]).is_ok()
+	}
+
+	#[test]
+	fn block() {
+		let events: Arc<std::sync::Mutex<VecDeque<ServerEvent>>> = Default::default();
+		let server = create_server(RaknetToUserThreadEventSender::new(events.clone()), Default::default());
+		let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		let subnet = IpSubnet::new("127.0.0.0".parse().unwrap(), 8).unwrap();
+
+		assert!(pong(&server, &client));
+		server.internal.lock().block_subnet(subnet, Duration::from_secs(60));
+		assert!(!pong(&server, &client));
+		server.internal.lock().unblock_subnet(&subnet);
+		assert!(pong(&server, &client));
+		server.internal.lock().block_address(client.local_addr().unwrap().ip(), Duration::from_secs(60));
+		assert!(!pong(&server, &client));
+
+		let events = events.lock().unwrap();
+		assert_eq!(events.len(), 3);
+		assert!(matches!(events[0], ServerEvent::AddressBlock { subnet: blocked, .. } if blocked == subnet));
+		assert!(matches!(events[1], ServerEvent::AddressUnblock { subnet: unblocked } if unblocked == subnet));
+		assert!(matches!(events[2], ServerEvent::AddressBlock { subnet: blocked, .. } if blocked.get_prefix_len() == 32));
+	}
+
+	#[test]
+	fn shutdown_drain() {
+		let channel: Arc<Mutex<VecDeque<UserToRaknetMessage>>> = Default::default();
+		let server = create_server(RaknetToUserThreadEventSender::new(Default::default()), channel.clone());
+		let mut sender = UserToRaknetMessageSender::new(channel.clone());
+		sender.set_name("drain".to_owned());
+		sender.shutdown(Duration::from_secs(60));
+		server.tick_processor();
+		//the message behind the shutdown stays queued
+		assert!(server.internal.lock().shutdown);
+		assert_eq!(channel.lock().len(), 1);
+
+		//blocks still expire while draining
+		let subnet = IpSubnet::new("127.0.0.0".parse().unwrap(), 8).unwrap();
+		server.internal.lock().block_subnet(subnet, Duration::from_millis(1));
+		sleep(Duration::from_millis(5));
+		for _ in 0..=server.config.tick_rate() {
+			server.internal.lock().tick();
+		}
+		assert!(!server.internal.lock().is_blocked(&"127.0.0.1".parse().unwrap()));
+	}
+
+	#[test]
+	fn rate_limit() {
+		let server = Server::new(
+			0,
+			UdpSocket::bind("127.0.0.1:0").unwrap(),
+			ServerConfig::builder().packet_per_tick_limit(10).build().unwrap(),
+			PA,
+			UserToRaknetMessageReceiver::new(Default::default()),
+			RaknetToUserThreadEventSender::new(Default::default())
+		).unwrap();
+		let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		let ip = client.local_addr().unwrap().ip();
+
+		for _ in 0..10 {
+			assert!(pong(&server, &client));
+		}
+		assert_eq!(server.internal.lock().get_packet_counts()[&ip], 10);
+		server.internal.lock().tick();
+		assert!(server.internal.lock().get_packet_counts().is_empty());
+
+		for _ in 0..10 {
+			assert!(pong(&server, &client));
+		}
+		assert!(!pong(&server, &client));
+		let internal = server.internal.lock();
+		assert!(internal.is_blocked(&ip));
+		assert_eq!(internal.get_rate_limit_blocks()[&ip], 1);
+	}
+
+	/// Returns the dropped and deferred packet counts of the next bandwidth stats update
+	fn overflow(policy: PacketOverflowPolicy, answered_after_tick: bool) -> (usize, usize) {
+		let events: Arc<std::sync::Mutex<VecDeque<ServerEvent>>> = Default::default();
+		let server = Server::new(
+			0,
+			UdpSocket::bind("127.0.0.1:0").unwrap(),
+			ServerConfig::builder().max_datagrams_per_tick(2).packet_overflow_policy(policy).build().unwrap(),
+			PA,
+			UserToRaknetMessageReceiver::new(Default::default()),
+			RaknetToUserThreadEventSender::new(events.clone())
+		).unwrap();
+		let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+
+		assert!(pong(&server, &client));
+		assert!(pong(&server, &client));
+		assert!(!pong(&server, &client));
+		server.internal.lock().tick();
+		assert_eq!(client.recv(&mut [0; 1500]).is_ok(), answered_after_tick);
+
+		for _ in 0..server.config.tick_rate() {
+			server.internal.lock().tick();
+		}
+		let events = events.lock().unwrap();
+		match events.front() {
+			Some(ServerEvent::BandwidthStatsUpdate { dropped_packets, deferred_packets, .. }) => (*dropped_packets, *deferred_packets),
+			_ => panic!("expected bandwidth stats")
+		}
+	}
+
+	#[test]
+	fn packet_overflow() {
+		assert_eq!(overflow(PacketOverflowPolicy::Queue, true), (0, 1));
+		assert_eq!(overflow(PacketOverflowPolicy::Drop, false), (1, 0));
+	}
+}
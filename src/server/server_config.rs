@@ -0,0 +1,541 @@
+use std::time::Duration;
+use crate::generic::{ReceiveReliabilityLayer, SendReliabilityLayer, CongestionController, SlidingWindow};
+#[cfg(feature = "security")]
+use crate::generic::KeyPair;
+use crate::server::{ServerConfigError, PacketOverflowPolicy};
+use crate::protocol::AcknowledgePacket;
+
+/**
+ * Timeouts and limits of a `Server`, shared with every session.
+ * Use `ServerConfig::builder()` to change the defaults.
+ */
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+	tick_rate: u32,
+	max_mtu_size: usize,
+	min_mtu_size: usize,
+	session_timeout: Duration,
+	ping_interval: Duration,
+	max_split_part_count: usize,
+	max_concurrent_split_count: usize,
+	max_split_buffer_size: usize,
+	split_timeout: Duration,
+	ack_delay: Duration,
+	ack_batch_size: usize,
+	nack_delay: Duration,
+	resend_timeout: Duration,
+	resend_limit: usize,
+	receive_window_size: usize,
+	session_gc_threshold: usize,
+	max_connections: usize,
+	max_connections_per_ip: usize,
+	ip_reconnect_interval: Duration,
+	packet_per_tick_limit: usize,
+	rate_limit_block_duration: Duration,
+	max_datagrams_per_tick: usize,
+	max_session_datagrams_per_tick: usize,
+	packet_overflow_policy: PacketOverflowPolicy,
+	use_cookies: bool,
+	cookie_rotation_interval: Duration,
+	#[cfg(feature = "security")]
+	key_pair: Option<KeyPair>,
+	congestion_controller: fn(usize) -> Box<dyn CongestionController>
+}
+
+impl ServerConfig {
+
+	/// Smallest MTU which still leaves room for the datagram and encapsulation headers
+	pub const MIN_SUPPORTED_MTU_SIZE: usize = 128;
+
+	/// Largest receive window, a single ACK can't acknowledge more datagrams. Also well below half of the sequence number space.
+	pub const MAX_RECEIVE_WINDOW_SIZE: usize = AcknowledgePacket::MAX_SEQUENCE_NUMBERS;
+
+	/// Largest split part limit, a packet of that many full parts is already over 10 MB
+	pub const MAX_SPLIT_PART_COUNT: usize = 8192;
+
+	pub fn builder() -> ServerConfigBuilder {
+		ServerConfigBuilder::default()
+	}
+
+	/**
+	 * Ticks per second, default 100
+	 */
+	pub fn tick_rate(&self) -> u32 {
+		self.tick_rate
+	}
+
+	pub fn tick_interval(&self) -> Duration {
+		Duration::from_secs(1) / self.tick_rate
+	}
+
+	/**
+	 * Largest MTU accepted from clients, default 1492
+	 */
+	pub fn max_mtu_size(&self) -> usize {
+		self.max_mtu_size
+	}
+
+	/**
+	 * Connection requests with a smaller MTU are refused, default 400
+	 */
+	pub fn min_mtu_size(&self) -> usize {
+		self.min_mtu_size
+	}
+
+	/**
+	 * Sessions which didn't send anything for this long are dropped, default 10 seconds.
+	 * Also limits how long a graceful disconnect may take.
+	 */
+	pub fn session_timeout(&self) -> Duration {
+		self.session_timeout
+	}
+
+	/**
+	 * Default 5 seconds
+	 */
+	pub fn ping_interval(&self) -> Duration {
+		self.ping_interval
+	}
+
+	/**
+	 * Split packets with more parts are neither sent nor accepted, default 128.
+	 * Clients sending larger packets must use the same limit, see `Client::set_max_split_part_count`.
+	 */
+	pub fn max_split_part_count(&self) -> usize {
+		self.max_split_part_count
+	}
+
+	/**
+	 * Split packets a session may reassemble at once, default 4
+	 */
+	pub fn max_concurrent_split_count(&self) -> usize {
+		self.max_concurrent_split_count
+	}
+
+	/**
+	 * Bytes the incomplete split packets of a session may buffer, default 1 MiB.
+	 * Raise it together with `max_split_part_count`.
+	 */
+	pub fn max_split_buffer_size(&self) -> usize {
+		self.max_split_buffer_size
+	}
+
+	/**
+	 * Incomplete split packets are discarded after this time, default 10 seconds
+	 */
+	pub fn split_timeout(&self) -> Duration {
+		self.split_timeout
+	}
+
+	/**
+	 * ACKs are held back for at most this time to be batched, unless they can be sent along with outgoing datagrams.
+	 * Default 10 milliseconds, zero sends them every tick.
+	 */
+	pub fn ack_delay(&self) -> Duration {
+		self.ack_delay
+	}
+
+	/**
+	 * ACKs are sent right away once this many datagrams are waiting for one, default 32
+	 */
+	pub fn ack_batch_size(&self) -> usize {
+		self.ack_batch_size
+	}
+
+	/**
+	 * Reordering threshold, a missing datagram is only NACKed once the gap lasted this long. Default 10 milliseconds.
+	 */
+	pub fn nack_delay(&self) -> Duration {
+		self.nack_delay
+	}
+
+	/**
+	 * Upper bound of the retransmission timeout, which adapts to the round trip time of each session.
+	 * Default 8 seconds.
+	 */
+	pub fn resend_timeout(&self) -> Duration {
+		self.resend_timeout
+	}
+
+	/**
+	 * Datagrams resent per session and tick, default 16
+	 */
+	pub fn resend_limit(&self) -> usize {
+		self.resend_limit
+	}
+
+	/**
+	 * Default 2048 datagrams
+	 */
+	pub fn receive_window_size(&self) -> usize {
+		self.receive_window_size
+	}
+
+	/**
+	 * Unconnected sessions are collected once more sessions than this are open, default 4096
+	 */
+	pub fn session_gc_threshold(&self) -> usize {
+		self.session_gc_threshold
+	}
+
+	/**
+	 * Clients are answered with `NoFreeIncomingConnections` once this many sessions are open, default unlimited
+	 */
+	pub fn max_connections(&self) -> usize {
+		self.max_connections
+	}
+
+	/**
+	 * Clients are answered with `ConnectionBanned` once their IP has this many sessions open, default unlimited
+	 */
+	pub fn max_connections_per_ip(&self) -> usize {
+		self.max_connections_per_ip
+	}
+
+	/**
+	 * Clients are answered with `IpRecentlyConnected` if their IP opened a session within this time.
+	 * Default zero, which disables the throttling.
+	 */
+	pub fn ip_reconnect_interval(&self) -> Duration {
+		self.ip_reconnect_interval
+	}
+
+	/**
+	 * Addresses sending more packets within a tick are blocked, default 200.
+	 * Can be changed at runtime with `ServerInterface::set_packet_per_tick_limit`.
+	 */
+	pub fn packet_per_tick_limit(&self) -> usize {
+		self.packet_per_tick_limit
+	}
+
+	/**
+	 * How long addresses exceeding the packet limit are blocked, default 5 minutes
+	 */
+	pub fn rate_limit_block_duration(&self) -> Duration {
+		self.rate_limit_block_duration
+	}
+
+	/**
+	 * Datagrams processed per tick from all addresses together, default 2048.
+	 * This is also the size of the overflow queue.
+	 */
+	pub fn max_datagrams_per_tick(&self) -> usize {
+		self.max_datagrams_per_tick
+	}
+
+	/**
+	 * Datagrams processed per tick from a single session, default 100
+	 */
+	pub fn max_session_datagrams_per_tick(&self) -> usize {
+		self.max_session_datagrams_per_tick
+	}
+
+	/**
+	 * Default `PacketOverflowPolicy::Queue`
+	 */
+	pub fn packet_overflow_policy(&self) -> PacketOverflowPolicy {
+		self.packet_overflow_policy
+	}
+
+	/**
+	 * Whether clients have to echo a cookie sent in `OpenConnectionReply1` before a session is created for them,
+	 * so spoofed source addresses can't allocate sessions. Default false.
+	 */
+	pub fn use_cookies(&self) -> bool {
+		self.use_cookies
+	}
+
+	/**
+	 * The cookie secret is replaced after this time, cookies stay valid for up to two intervals. Default 60 seconds.
+	 */
+	pub fn cookie_rotation_interval(&self) -> Duration {
+		self.cookie_rotation_interval
+	}
+
+	/**
+	 * Key pair clients exchange keys with to encrypt their sessions. Clients can pin its public key. Default None.
+	 */
+	#[cfg(feature = "security")]
+	pub fn key_pair(&self) -> Option<&KeyPair> {
+		self.key_pair.as_ref()
+	}
+
+	/**
+	 * Whether sessions are encrypted, which requires the `security` feature and a key pair
+	 */
+	pub fn has_security(&self) -> bool {
+		#[cfg(feature = "security")]
+		return self.key_pair.is_some();
+		#[cfg(not(feature = "security"))]
+		return false;
+	}
+
+	/**
+	 * Creates the congestion controller of a session with the given MTU size, default `SlidingWindow`
+	 */
+	pub fn create_congestion_controller(&self, mtu_size: usize) -> Box<dyn CongestionController> {
+		(self.congestion_controller)(mtu_size)
+	}
+}
+
+impl Default for ServerConfig {
+	fn default() -> Self {
+		Self {
+			tick_rate: 100,
+			max_mtu_size: 1492,
+			min_mtu_size: 400,
+			session_timeout: Duration::from_secs(10),
+			ping_interval: Duration::from_secs(5),
+			max_split_part_count: SendReliabilityLayer::DEFAULT_MAX_SPLIT_PART_COUNT,
+			max_concurrent_split_count: 4,
+			max_split_buffer_size: ReceiveReliabilityLayer::DEFAULT_MAX_SPLIT_BUFFER_SIZE,
+			split_timeout: ReceiveReliabilityLayer::DEFAULT_SPLIT_TIMEOUT,
+			ack_delay: ReceiveReliabilityLayer::DEFAULT_ACK_DELAY,
+			ack_batch_size: ReceiveReliabilityLayer::DEFAULT_ACK_BATCH_SIZE,
+			nack_delay: ReceiveReliabilityLayer::DEFAULT_NACK_DELAY,
+			resend_timeout: SendReliabilityLayer::DEFAULT_RESEND_TIMEOUT,
+			resend_limit: SendReliabilityLayer::DEFAULT_RESEND_LIMIT,
+			receive_window_size: ReceiveReliabilityLayer::WINDOW_SIZE,
+			session_gc_threshold: 4096,
+			max_connections: usize::MAX,
+			max_connections_per_ip: usize::MAX,
+			ip_reconnect_interval: Duration::ZERO,
+			packet_per_tick_limit: 200,
+			rate_limit_block_duration: Duration::from_secs(300),
+			max_datagrams_per_tick: 2048,
+			max_session_datagrams_per_tick: 100,
+			packet_overflow_policy: PacketOverflowPolicy::default(),
+			use_cookies: false,
+			cookie_rotation_interval: Duration::from_secs(60),
+			#[cfg(feature = "security")]
+			key_pair: None,
+			congestion_controller: | mtu_size | Box::new(SlidingWindow::new(mtu_size))
+		}
+	}
+}
+
+#[derive(Default)]
+pub struct ServerConfigBuilder {
+	config: ServerConfig
+}
+
+impl ServerConfigBuilder {
+	pub fn tick_rate(mut self, tick_rate: u32) -> Self {
+		self.config.tick_rate = tick_rate;
+		self
+	}
+
+	pub fn max_mtu_size(mut self, max_mtu_size: usize) -> Self {
+		self.config.max_mtu_size = max_mtu_size;
+		self
+	}
+
+	pub fn min_mtu_size(mut self, min_mtu_size: usize) -> Self {
+		self.config.min_mtu_size = min_mtu_size;
+		self
+	}
+
+	pub fn session_timeout(mut self, session_timeout: Duration) -> Self {
+		self.config.session_timeout = session_timeout;
+		self
+	}
+
+	pub fn ping_interval(mut self, ping_interval: Duration) -> Self {
+		self.config.ping_interval = ping_interval;
+		self
+	}
+
+	pub fn max_split_part_count(mut self, max_split_part_count: usize) -> Self {
+		self.config.max_split_part_count = max_split_part_count;
+		self
+	}
+
+	pub fn max_concurrent_split_count(mut self, max_concurrent_split_count: usize) -> Self {
+		self.config.max_concurrent_split_count = max_concurrent_split_count;
+		self
+	}
+
+	pub fn max_split_buffer_size(mut self, max_split_buffer_size: usize) -> Self {
+		self.config.max_split_buffer_size = max_split_buffer_size;
+		self
+	}
+
+	pub fn split_timeout(mut self, split_timeout: Duration) -> Self {
+		self.config.split_timeout = split_timeout;
+		self
+	}
+
+	pub fn ack_delay(mut self, ack_delay: Duration) -> Self {
+		self.config.ack_delay = ack_delay;
+		self
+	}
+
+	pub fn ack_batch_size(mut self, ack_batch_size: usize) -> Self {
+		self.config.ack_batch_size = ack_batch_size;
+		self
+	}
+
+	pub fn nack_delay(mut self, nack_delay: Duration) -> Self {
+		self.config.nack_delay = nack_delay;
+		self
+	}
+
+	pub fn resend_timeout(mut self, resend_timeout: Duration) -> Self {
+		self.config.resend_timeout = resend_timeout;
+		self
+	}
+
+	pub fn resend_limit(mut self, resend_limit: usize) -> Self {
+		self.config.resend_limit = resend_limit;
+		self
+	}
+
+	pub fn receive_window_size(mut self, receive_window_size: usize) -> Self {
+		self.config.receive_window_size = receive_window_size;
+		self
+	}
+
+	pub fn session_gc_threshold(mut self, session_gc_threshold: usize) -> Self {
+		self.config.session_gc_threshold = session_gc_threshold;
+		self
+	}
+
+	pub fn max_connections(mut self, max_connections: usize) -> Self {
+		self.config.max_connections = max_connections;
+		self
+	}
+
+	pub fn max_connections_per_ip(mut self, max_connections_per_ip: usize) -> Self {
+		self.config.max_connections_per_ip = max_connections_per_ip;
+		self
+	}
+
+	pub fn ip_reconnect_interval(mut self, ip_reconnect_interval: Duration) -> Self {
+		self.config.ip_reconnect_interval = ip_reconnect_interval;
+		self
+	}
+
+	pub fn packet_per_tick_limit(mut self, packet_per_tick_limit: usize) -> Self {
+		self.config.packet_per_tick_limit = packet_per_tick_limit;
+		self
+	}
+
+	pub fn rate_limit_block_duration(mut self, rate_limit_block_duration: Duration) -> Self {
+		self.config.rate_limit_block_duration = rate_limit_block_duration;
+		self
+	}
+
+	pub fn max_datagrams_per_tick(mut self, max_datagrams_per_tick: usize) -> Self {
+		self.config.max_datagrams_per_tick = max_datagrams_per_tick;
+		self
+	}
+
+	pub fn max_session_datagrams_per_tick(mut self, max_session_datagrams_per_tick: usize) -> Self {
+		self.config.max_session_datagrams_per_tick = max_session_datagrams_per_tick;
+		self
+	}
+
+	pub fn packet_overflow_policy(mut self, packet_overflow_policy: PacketOverflowPolicy) -> Self {
+		self.config.packet_overflow_policy = packet_overflow_policy;
+		self
+	}
+
+	pub fn use_cookies(mut self, use_cookies: bool) -> Self {
+		self.config.use_cookies = use_cookies;
+		self
+	}
+
+	pub fn cookie_rotation_interval(mut self, cookie_rotation_interval: Duration) -> Self {
+		self.config.cookie_rotation_interval = cookie_rotation_interval;
+		self
+	}
+
+	#[cfg(feature = "security")]
+	pub fn key_pair(mut self, key_pair: KeyPair) -> Self {
+		self.config.key_pair = Some(key_pair);
+		self
+	}
+
+	pub fn congestion_controller(mut self, congestion_controller: fn(usize) -> Box<dyn CongestionController>) -> Self {
+		self.config.congestion_controller = congestion_controller;
+		self
+	}
+
+	pub fn build(self) -> Result<ServerConfig, ServerConfigError> {
+		let config = self.config;
+		if config.tick_rate == 0 || config.tick_rate > 1000 {
+			return Err(ServerConfigError::InvalidTickRate(config.tick_rate));
+		}
+		if config.min_mtu_size < ServerConfig::MIN_SUPPORTED_MTU_SIZE || config.min_mtu_size > config.max_mtu_size || config.max_mtu_size > u16::MAX as usize {
+			return Err(ServerConfigError::MtuSizeOutOfRange {
+				min_mtu_size: config.min_mtu_size,
+				max_mtu_size: config.max_mtu_size
+			});
+		}
+		for (name, zero) in [
+			("session timeout", config.session_timeout.is_zero()),
+			("ping interval", config.ping_interval.is_zero()),
+			("max split part count", config.max_split_part_count == 0),
+			("max concurrent split count", config.max_concurrent_split_count == 0),
+			("max split buffer size", config.max_split_buffer_size == 0),
+			("split timeout", config.split_timeout.is_zero()),
+			("ack batch size", config.ack_batch_size == 0),
+			("resend timeout", config.resend_timeout.is_zero()),
+			("resend limit", config.resend_limit == 0),
+			("receive window size", config.receive_window_size == 0),
+			("max connections", config.max_connections == 0),
+			("max connections per ip", config.max_connections_per_ip == 0),
+			("packet per tick limit", config.packet_per_tick_limit == 0),
+			("rate limit block duration", config.rate_limit_block_duration.is_zero()),
+			("max datagrams per tick", config.max_datagrams_per_tick == 0),
+			("max session datagrams per tick", config.max_session_datagrams_per_tick == 0),
+			("cookie rotation interval", config.cookie_rotation_interval.is_zero())
+		] {
+			if zero {
+				return Err(ServerConfigError::Zero(name));
+			}
+		}
+		if config.max_split_part_count > ServerConfig::MAX_SPLIT_PART_COUNT {
+			return Err(ServerConfigError::SplitPartCountTooLarge(config.max_split_part_count));
+		}
+		if config.receive_window_size > ServerConfig::MAX_RECEIVE_WINDOW_SIZE {
+			return Err(ServerConfigError::ReceiveWindowTooLarge(config.receive_window_size));
+		}
+		if config.ping_interval >= config.session_timeout {
+			return Err(ServerConfigError::PingIntervalTooLong);
+		}
+		Ok(config)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use crate::server::{ServerConfig, ServerConfigError};
+
+	#[test]
+	fn defaults_are_valid() {
+		let config = ServerConfig::builder().build().unwrap();
+		assert_eq!(config.tick_interval(), Duration::from_millis(10));
+		assert_eq!(config.session_timeout(), Duration::from_secs(10));
+	}
+
+	//sequence numbers are 24 bits, the window must not cover half of them
+	const _: () = assert!(ServerConfig::MAX_RECEIVE_WINDOW_SIZE < 1 << 23);
+
+	#[test]
+	fn validation() {
+		assert_eq!(ServerConfig::builder().tick_rate(0).build().unwrap_err(), ServerConfigError::InvalidTickRate(0));
+		assert_eq!(ServerConfig::builder().min_mtu_size(1500).max_mtu_size(1400).build().unwrap_err(), ServerConfigError::MtuSizeOutOfRange {
+			min_mtu_size: 1500,
+			max_mtu_size: 1400
+		});
+		assert_eq!(ServerConfig::builder().resend_limit(0).build().unwrap_err(), ServerConfigError::Zero("resend limit"));
+		assert_eq!(ServerConfig::builder().max_connections(0).build().unwrap_err(), ServerConfigError::Zero("max connections"));
+		assert!(ServerConfig::builder().receive_window_size(ServerConfig::MAX_RECEIVE_WINDOW_SIZE).build().is_ok());
+		assert!(ServerConfig::builder().max_split_part_count(ServerConfig::MAX_SPLIT_PART_COUNT).build().is_ok());
+		assert_eq!(ServerConfig::builder().max_split_part_count(ServerConfig::MAX_SPLIT_PART_COUNT + 1).build().unwrap_err(), ServerConfigError::SplitPartCountTooLarge(ServerConfig::MAX_SPLIT_PART_COUNT + 1));
+		assert_eq!(ServerConfig::builder().receive_window_size(ServerConfig::MAX_RECEIVE_WINDOW_SIZE + 1).build().unwrap_err(), ServerConfigError::ReceiveWindowTooLarge(ServerConfig::MAX_RECEIVE_WINDOW_SIZE + 1));
+		assert_eq!(ServerConfig::builder().ping_interval(Duration::from_secs(10)).build().unwrap_err(), ServerConfigError::PingIntervalTooLong);
+	}
+}
@@ -0,0 +1,36 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+use crate::server::ServerConfig;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ServerConfigError {
+	/// Tick rate must be between 1 and 1000 ticks per second
+	InvalidTickRate(u32),
+	MtuSizeOutOfRange {
+		min_mtu_size: usize,
+		max_mtu_size: usize
+	},
+	/// The named setting must be greater than zero
+	Zero(&'static str),
+	/// Sessions would time out before a ping is sent
+	PingIntervalTooLong,
+	/// Receive window size must not exceed `ServerConfig::MAX_RECEIVE_WINDOW_SIZE`
+	ReceiveWindowTooLarge(usize),
+	/// Max split part count must not exceed `ServerConfig::MAX_SPLIT_PART_COUNT`
+	SplitPartCountTooLarge(usize)
+}
+
+impl Display for ServerConfigError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ServerConfigError::InvalidTickRate(tick_rate) => write!(f, "tick rate must be between 1 and 1000, got {}", tick_rate),
+			ServerConfigError::MtuSizeOutOfRange { min_mtu_size, max_mtu_size } => write!(f, "invalid MTU size range {}..={}", min_mtu_size, max_mtu_size),
+			ServerConfigError::Zero(name) => write!(f, "{} must be greater than zero", name),
+			ServerConfigError::PingIntervalTooLong => write!(f, "ping interval must be shorter than the session timeout"),
+			ServerConfigError::ReceiveWindowTooLarge(receive_window_size) => write!(f, "receive window size must be at most {}, got {}", ServerConfig::MAX_RECEIVE_WINDOW_SIZE, receive_window_size),
+			ServerConfigError::SplitPartCountTooLarge(max_split_part_count) => write!(f, "max split part count must be at most {}, got {}", ServerConfig::MAX_SPLIT_PART_COUNT, max_split_part_count)
+		}
+	}
+}
+
+impl Error for ServerConfigError {}
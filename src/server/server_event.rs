@@ -1,6 +1,7 @@
 
 use std::net::SocketAddr;
 use std::time::Duration;
+use crate::server::IpSubnet;
 
 pub enum ServerEvent {
 	PacketReceive {
@@ -20,9 +21,15 @@ pub enum ServerEvent {
 		session_id: usize,
 		identifier_ack: u64,
 	},
+	PacketLoss {
+		session_id: usize,
+		identifier_ack: u64
+	},
 	BandwidthStatsUpdate {
 		bytes_sent_diff: usize,
-		bytes_received_diff: usize
+		bytes_received_diff: usize,
+		dropped_packets: usize,
+		deferred_packets: usize
 	}
 	,
 	RawPacketReceive {
@@ -32,5 +39,12 @@ pub enum ServerEvent {
 	PingMeasure {
 		session_id: usize,
 		latency: Duration
+	},
+	AddressBlock {
+		subnet: IpSubnet,
+		timeout: Duration
+	},
+	AddressUnblock {
+		subnet: IpSubnet
 	}
 }
\ No newline at end of file
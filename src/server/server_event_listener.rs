@@ -1,6 +1,6 @@
 use std::net::SocketAddr;
 use std::time::Duration;
-use crate::server::ServerEvent;
+use crate::server::{ServerEvent, IpSubnet};
 
 pub trait ServerEventListener: Send + Sync {
 	fn handle_event(&mut self, event: ServerEvent) {
@@ -42,12 +42,23 @@ pub trait ServerEventListener: Send + Sync {
 				session_id,
 				identifier_ack
 			),
+			ServerEvent::PacketLoss {
+				session_id,
+				identifier_ack
+			} => self.on_packet_loss(
+				session_id,
+				identifier_ack
+			),
 			ServerEvent::BandwidthStatsUpdate {
 				bytes_sent_diff,
-				bytes_received_diff
+				bytes_received_diff,
+				dropped_packets,
+				deferred_packets
 			} => self.on_bandwidth_stats_update(
 				bytes_sent_diff,
-				bytes_received_diff
+				bytes_received_diff,
+				dropped_packets,
+				deferred_packets
 			),
 			ServerEvent::PingMeasure {
 				session_id,
@@ -55,7 +66,17 @@ pub trait ServerEventListener: Send + Sync {
 			} => self.on_ping_measure(
 				session_id,
 				latency
-			)
+			),
+			ServerEvent::AddressBlock {
+				subnet,
+				timeout
+			} => self.on_address_block(
+				subnet,
+				timeout
+			),
+			ServerEvent::AddressUnblock {
+				subnet
+			} => self.on_address_unblock(subnet)
 		}
 	}
 
@@ -63,7 +84,22 @@ pub trait ServerEventListener: Send + Sync {
 	fn on_client_disconnect(&mut self, session_id: usize, reason: &str);
 	fn on_packet_receive(&mut self, session_id: usize, packet: &[u8]);
 	fn on_raw_packet_receive(&mut self, address: SocketAddr, payload: &[u8]);
+	/**
+	 * Called when every part of a packet sent with an ack receipt reliability was acknowledged
+	 */
 	fn on_packet_ack(&mut self, session_id: usize, identifier_ack: u64);
-	fn on_bandwidth_stats_update(&mut self, bytes_sent_diff: usize, bytes_received_diff: usize);
+	/**
+	 * Called when a packet sent with `UnreliableWithAckReceipt` was lost, reliable packets are resent instead
+	 */
+	fn on_packet_loss(&mut self, session_id: usize, identifier_ack: u64);
+	/**
+	 * Called once per second, `dropped_packets` and `deferred_packets` count datagrams which exceeded the processing limits
+	 */
+	fn on_bandwidth_stats_update(&mut self, bytes_sent_diff: usize, bytes_received_diff: usize, dropped_packets: usize, deferred_packets: usize);
 	fn on_ping_measure(&mut self, session_id: usize, latency: Duration);
+	/**
+	 * Packets from the subnet are dropped and its sessions are disconnected until the timeout passes
+	 */
+	fn on_address_block(&mut self, subnet: IpSubnet, timeout: Duration);
+	fn on_address_unblock(&mut self, subnet: IpSubnet);
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use futures_core::Stream;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::mpsc::error::TrySendError;
+use crate::server::{ServerEvent, ServerEventListener, IpSubnet};
+
+/**
+ * Events of an `AsyncServer`, in the order they were raised.
+ * The server doesn't wait for a slow reader, events which don't fit in the channel are dropped.
+ */
+pub struct ServerEventStream {
+	receiver: Receiver<ServerEvent>,
+
+	dropped_events: Arc<AtomicUsize>
+}
+
+impl ServerEventStream {
+	pub(super) fn new(receiver: Receiver<ServerEvent>, dropped_events: Arc<AtomicUsize>) -> Self {
+		Self {
+			receiver,
+			dropped_events
+		}
+	}
+
+	/**
+	 * Events dropped because the channel was full
+	 */
+	pub fn get_dropped_events(&self) -> usize {
+		self.dropped_events.load(Ordering::Relaxed)
+	}
+}
+
+impl Stream for ServerEventStream {
+	type Item = ServerEvent;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.receiver.poll_recv(cx)
+	}
+}
+
+pub(super) struct ServerEventStreamSender {
+	sender: Sender<ServerEvent>,
+
+	dropped_events: Arc<AtomicUsize>
+}
+
+impl ServerEventStreamSender {
+	pub fn new(sender: Sender<ServerEvent>, dropped_events: Arc<AtomicUsize>) -> Self {
+		Self {
+			sender,
+			dropped_events
+		}
+	}
+}
+
+impl ServerEventListener for ServerEventStreamSender {
+	#[inline]
+	fn handle_event(&mut self, event: ServerEvent) {
+		match self.sender.try_send(event) {
+			Err(TrySendError::Full(_)) => {
+				self.dropped_events.fetch_add(1, Ordering::Relaxed);
+			},
+			//the stream was dropped, nobody is interested in events anymore
+			Err(TrySendError::Closed(_)) | Ok(()) => {}
+		}
+	}
+
+	#[inline]
+	fn on_client_connect(&mut self, session_id: usize, address: SocketAddr, client_id: u64) {
+		self.handle_event(ServerEvent::ClientConnect {
+			session_id,
+			address,
+			client_id
+		})
+	}
+
+	#[inline]
+	fn on_client_disconnect(&mut self, session_id: usize, reason: &str) {
+		self.handle_event(ServerEvent::ClientDisconnect {
+			session_id,
+			reason: reason.to_owned()
+		})
+	}
+
+	#[inline]
+	fn on_packet_receive(&mut self, session_id: usize, packet: &[u8]) {
+		self.handle_event(ServerEvent::PacketReceive {
+			session_id,
+			packet: packet.to_owned()
+		})
+	}
+
+	#[inline]
+	fn on_raw_packet_receive(&mut self, address: SocketAddr, payload: &[u8]) {
+		self.handle_event(ServerEvent::RawPacketReceive {
+			address,
+			payload: payload.to_owned()
+		})
+	}
+
+	#[inline]
+	fn on_packet_ack(&mut self, session_id: usize, identifier_ack: u64) {
+		self.handle_event(ServerEvent::PacketAck {
+			session_id,
+			identifier_ack
+		})
+	}
+
+	#[inline]
+	fn on_packet_loss(&mut self, session_id: usize, identifier_ack: u64) {
+		self.handle_event(ServerEvent::PacketLoss {
+			session_id,
+			identifier_ack
+		})
+	}
+
+	#[inline]
+	fn on_bandwidth_stats_update(&mut self, bytes_sent_diff: usize, bytes_received_diff: usize, dropped_packets: usize, deferred_packets: usize) {
+		self.handle_event(ServerEvent::BandwidthStatsUpdate {
+			bytes_sent_diff,
+			bytes_received_diff,
+			dropped_packets,
+			deferred_packets
+		})
+	}
+
+	#[inline]
+	fn on_ping_measure(&mut self, session_id: usize, latency: Duration) {
+		self.handle_event(ServerEvent::PingMeasure {
+			session_id,
+			latency
+		})
+	}
+
+	#[inline]
+	fn on_address_block(&mut self, subnet: IpSubnet, timeout: Duration) {
+		self.handle_event(ServerEvent::AddressBlock {
+			subnet,
+			timeout
+		})
+	}
+
+	#[inline]
+	fn on_address_unblock(&mut self, subnet: IpSubnet) {
+		self.handle_event(ServerEvent::AddressUnblock {
+			subnet
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use tokio::sync::mpsc::channel;
+	use crate::server::{ServerEventListener, ServerEventStream};
+	use crate::server::server_event_stream::ServerEventStreamSender;
+
+	#[test]
+	fn full_channel() {
+		let dropped_events = Arc::new(Default::default());
+		let (sender, mut receiver) = channel(2);
+		let mut listener = ServerEventStreamSender::new(sender, Arc::clone(&dropped_events));
+		for session_id in 0..5 {
+			listener.on_packet_ack(session_id, 0);
+		}
+		receiver.try_recv().unwrap();
+		listener.on_packet_ack(5, 0);
+		let stream = ServerEventStream::new(receiver, dropped_events);
+		assert_eq!(stream.get_dropped_events(), 3);
+	}
+}
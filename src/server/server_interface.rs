@@ -3,6 +3,7 @@ use std::net::{SocketAddr, IpAddr};
 use std::time::Duration;
 use regex::bytes::Regex;
 use crate::server::ipc::UserToRaknetMessage;
+use crate::server::IpSubnet;
 
 pub trait ServerInterface {
 
@@ -38,7 +39,18 @@ pub trait ServerInterface {
 				timeout
 			),
 			UserToRaknetMessage::UnblockAddress(address) => self.unblock_address(&address),
-			UserToRaknetMessage::RawFilter(regex) => self.add_raw_packet_filter(regex)
+			UserToRaknetMessage::BlockSubnet {
+				subnet,
+				timeout
+			} => self.block_subnet(
+				subnet,
+				timeout
+			),
+			UserToRaknetMessage::UnblockSubnet(subnet) => self.unblock_subnet(&subnet),
+			UserToRaknetMessage::RawFilter(regex) => self.add_raw_packet_filter(regex),
+			UserToRaknetMessage::Shutdown {
+				timeout
+			} => self.shutdown(timeout)
 		}
 	}
 
@@ -50,5 +62,12 @@ pub trait ServerInterface {
 	fn set_packet_per_tick_limit(&mut self, limit: usize);
 	fn block_address(&mut self, address: IpAddr, timeout: Duration);
 	fn unblock_address(&mut self, address: &IpAddr);
+	fn block_subnet(&mut self, subnet: IpSubnet, timeout: Duration);
+	fn unblock_subnet(&mut self, subnet: &IpSubnet);
 	fn add_raw_packet_filter(&mut self, regex: Regex);
+	/**
+	 * Stops accepting new connections and gracefully disconnects every session.
+	 * Sessions which did not finish disconnecting within `timeout` are dropped.
+	 */
+	fn shutdown(&mut self, timeout: Duration);
 }
\ No newline at end of file
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::net::{UdpSocket, SocketAddr, SocketAddrV6};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use parking_lot::Mutex;
+use log::debug;
+
+/**
+ * UDP socket bound by a server and the traffic it carried since the server started
+ */
+pub struct ServerSocket {
+	pub udp_socket: UdpSocket,
+
+	local_address: SocketAddr,
+
+	pending: Mutex<VecDeque<(Vec<u8>, SocketAddr)>>, //datagrams a non-blocking socket couldn't send yet
+
+	send_bytes: AtomicUsize,
+	send_datagrams: AtomicUsize,
+	receive_bytes: AtomicUsize,
+	receive_datagrams: AtomicUsize
+}
+
+impl ServerSocket {
+	const MAX_PENDING_DATAGRAMS: usize = 1024;
+
+	pub fn new(udp_socket: UdpSocket) -> io::Result<Self> {
+		let local_address = udp_socket.local_addr()?;
+		Ok(Self {
+			udp_socket,
+			local_address,
+			pending: Mutex::new(VecDeque::new()),
+			send_bytes: AtomicUsize::new(0),
+			send_datagrams: AtomicUsize::new(0),
+			receive_bytes: AtomicUsize::new(0),
+			receive_datagrams: AtomicUsize::new(0)
+		})
+	}
+
+	pub fn get_local_address(&self) -> SocketAddr {
+		self.local_address
+	}
+
+	pub fn get_port(&self) -> u16 {
+		self.local_address.port()
+	}
+
+	/**
+	 * IPv6 sockets are assumed to be dual-stack
+	 */
+	pub fn can_reach(&self, address: &SocketAddr) -> bool {
+		self.local_address.is_ipv6() || address.is_ipv4()
+	}
+
+	pub fn send_to(&self, buffer: &[u8], address: &SocketAddr) -> io::Result<usize> {
+		//IPv4 clients of a dual-stack socket have to be addressed as IPv4-mapped IPv6 addresses
+		let address = match address {
+			SocketAddr::V4(v4) if self.local_address.is_ipv6() => SocketAddr::V6(SocketAddrV6::new(v4.ip().to_ipv6_mapped(), v4.port(), 0, 0)),
+			_ => *address
+		};
+		let mut pending = self.pending.lock();
+		//keep the order of the datagrams waiting for a non-blocking socket
+		if pending.is_empty() {
+			match self.send_unbuffered(buffer, &address) {
+				Err(e) if e.kind() == ErrorKind::WouldBlock => {},
+				result => return result
+			}
+		}
+		if pending.len() >= Self::MAX_PENDING_DATAGRAMS {
+			return Err(ErrorKind::WouldBlock.into());
+		}
+		pending.push_back((buffer.to_vec(), address));
+		Ok(buffer.len())
+	}
+
+	fn send_unbuffered(&self, buffer: &[u8], address: &SocketAddr) -> io::Result<usize> {
+		let send = self.udp_socket.send_to(buffer, address)?;
+		self.send_bytes.fetch_add(send, Ordering::Relaxed);
+		self.send_datagrams.fetch_add(1, Ordering::Relaxed);
+		Ok(send)
+	}
+
+	pub fn has_pending(&self) -> bool {
+		!self.pending.lock().is_empty()
+	}
+
+	/**
+	 * Sends the datagrams a non-blocking socket would have blocked on, fails with `WouldBlock` if it still would
+	 */
+	pub fn flush_pending(&self) -> io::Result<()> {
+		let mut pending = self.pending.lock();
+		while let Some((buffer, address)) = pending.front() {
+			match self.send_unbuffered(buffer, address) {
+				Err(e) if e.kind() == ErrorKind::WouldBlock => return Err(e),
+				Err(e) => debug!("Dropped datagram to {}: {}", address, e),
+				Ok(_) => {}
+			}
+			pending.pop_front();
+		}
+		Ok(())
+	}
+
+	pub(super) fn on_receive(&self, bytes: usize) {
+		self.receive_bytes.fetch_add(bytes, Ordering::Relaxed);
+		self.receive_datagrams.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn get_send_bytes(&self) -> usize {
+		self.send_bytes.load(Ordering::Relaxed)
+	}
+
+	pub fn get_send_datagrams(&self) -> usize {
+		self.send_datagrams.load(Ordering::Relaxed)
+	}
+
+	pub fn get_receive_bytes(&self) -> usize {
+		self.receive_bytes.load(Ordering::Relaxed)
+	}
+
+	pub fn get_receive_datagrams(&self) -> usize {
+		self.receive_datagrams.load(Ordering::Relaxed)
+	}
+}
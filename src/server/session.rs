@@ -1,8 +1,8 @@
-use crate::protocol::{Datagram, PacketReliability, EncapsulatedPacket, ConnectedPing, ACK, NACK, PacketImpl, MessageIdentifiers, ConnectionRequest, MessageIdentifierHeader, DecodePacket, ConnectionRequestAccepted, NewIncomingConnection, DisconnectionNotification, ConnectedPong, EncodePacket};
+use crate::protocol::{Datagram, PacketReliability, EncapsulatedPacket, ConnectedPing, ACK, NACK, PacketImpl, MessageIdentifiers, ConnectionRequest, MessageIdentifierHeader, DecodePacket, ConnectionRequestAccepted, NewIncomingConnection, DisconnectionNotification, ConnectedPong, EncodePacket, DecodeError};
 
 use std::net::SocketAddr;
 use std::time::{SystemTime, Duration, Instant};
-use crate::generic::{ReceiveReliabilityLayer, SendReliabilityLayer};
+use crate::generic::{ReceiveReliabilityLayer, SendReliabilityLayer, RttEstimator, DatagramCipher, datagram_mtu_size};
 use log::{debug};
 use std::convert::TryFrom;
 use crate::server::session::SessionState::Disconnecting;
@@ -27,8 +27,8 @@ impl<'a> Deref for Session<'a> {
 }
 
 impl<'a> Session<'a> {
-	pub fn new(server: Arc<ServerExport<'a>>, address: SocketAddr, client_id: u64, mtu_size: usize, internal_id: usize) -> Self {
-		let immutable = Arc::new(SessionExport::new(server, address, client_id, mtu_size, internal_id));
+	pub fn new(server: Arc<ServerExport<'a>>, socket_id: usize, address: SocketAddr, client_id: u64, mtu_size: usize, internal_id: usize, cipher: Option<Arc<dyn DatagramCipher>>) -> Self {
+		let immutable = Arc::new(SessionExport::new(server, socket_id, address, client_id, mtu_size, internal_id, cipher));
 		Self {
 			internal: Mutex::new(SessionInternal::new(mtu_size, immutable.clone())),
 			export: immutable
@@ -64,38 +64,53 @@ impl<'a> Deref for SessionInternal<'a> {
 
 impl<'a> SessionInternal<'a> {
 
-	pub const MAX_SPLIT_PART_COUNT: usize = 128;
-	pub const MAX_CONCURRENT_SPLIT_COUNT: usize = 4;
-
-	pub const MIN_MTU_SIZE: usize = 400;
-
 	fn new(mtu_size: usize, export: Arc<SessionExport<'a>>) -> Self {
-		if mtu_size < Self::MIN_MTU_SIZE {
-			panic!("MTU size must be at least {}, got {}", Self::MIN_MTU_SIZE, mtu_size);
+		let config = &export.server.config;
+		if mtu_size < config.min_mtu_size() {
+			panic!("MTU size must be at least {}, got {}", config.min_mtu_size(), mtu_size);
 		}
+		let max_split_part_count = config.max_split_part_count();
+		let max_concurrent_split_count = config.max_concurrent_split_count();
+		let receive_window_size = config.receive_window_size();
+		let max_split_buffer_size = config.max_split_buffer_size();
+		let split_timeout = config.split_timeout();
+		let ack_delay = config.ack_delay();
+		let ack_batch_size = config.ack_batch_size();
+		let nack_delay = config.nack_delay();
+		let last_ping_time = Instant::now() - config.ping_interval(); // *never
+		let export_route = export.clone();
 		let export_clone = export.clone();
+		let mut recv_layer = ReceiveReliabilityLayer::with_limits(
+			move | pk | {
+				export_route.handle_encapsulated_packet_route(pk);
+			}, //TODO
+			move | pk | {
+				export_clone.send_packet(&pk);
+			}, //TODO
+			max_split_part_count,
+			max_concurrent_split_count,
+			receive_window_size
+		);
+		recv_layer.set_max_split_buffer_size(max_split_buffer_size);
+		recv_layer.set_split_timeout(split_timeout);
+		recv_layer.set_ack_delay(ack_delay);
+		recv_layer.set_ack_batch_size(ack_batch_size);
+		recv_layer.set_nack_delay(nack_delay);
+		//ACKs and NACKs are encrypted as well
+		recv_layer.set_mtu_size(datagram_mtu_size(mtu_size, export.cipher.as_deref()));
 		Self {
-			export: export.clone(),
+			export,
 
 			last_update: Instant::now(),
 			is_active: false,
-			last_ping_time: Instant::now() - Duration::from_secs(6), // *never
-
-			recv_layer: ReceiveReliabilityLayer::with_split_limit(
-				move | pk | {
-					export.handle_encapsulated_packet_route(pk);
-				}, //TODO
-				move | pk | {
-					export_clone.send_packet(&pk);
-				}, //TODO
-				Self::MAX_SPLIT_PART_COUNT,
-				Self::MAX_CONCURRENT_SPLIT_COUNT
-			),
+			last_ping_time,
+
+			recv_layer,
 		}
 	}
 
 	pub fn update(&mut self, time: Instant) {
-		let timeout = Duration::from_secs(10);
+		let timeout = self.server.config.session_timeout();
 		if !self.is_active && time.duration_since(self.last_update) > timeout {
 			self.forcibly_disconnect("timeout");
 
@@ -123,14 +138,17 @@ impl<'a> SessionInternal<'a> {
 			}
 		}
 
+		drop(state);
+
 		self.is_active = false;
 
-		self.recv_layer.update();
-		send_layer.update();
+		let is_sending = send_layer.update();
+		self.recv_layer.update(is_sending);
+		drop(send_layer);
 
-		if time.duration_since(self.last_ping_time) < Duration::from_secs(5) {
+		if time.duration_since(self.last_ping_time) >= self.server.config.ping_interval() {
 			self.send_ping();
-			self.last_ping_time = Instant::now();
+			self.last_ping_time = time;
 		}
 	}
 
@@ -192,8 +210,12 @@ pub struct SessionExport<'a> {
 
 	pub address: SocketAddr,
 
+	pub socket_id: usize, //index of the server socket the session connected on
+
 	pub internal_id: usize,
 
+	pub cipher: Option<Arc<dyn DatagramCipher>>, //set if the session negotiated security
+
 	state: Mutex<SessionState>, //default SessionState::Connecting
 
 	last_ping_measure: Mutex<Duration>, //default 1
@@ -204,25 +226,42 @@ pub struct SessionExport<'a> {
 }
 
 impl<'a> SessionExport<'a> {
-	pub fn new(server: Arc<ServerExport<'a>>, address: SocketAddr, client_id: u64, mtu_size: usize, internal_id: usize) -> Self {
+	pub fn new(server: Arc<ServerExport<'a>>, socket_id: usize, address: SocketAddr, client_id: u64, mtu_size: usize, internal_id: usize, cipher: Option<Arc<dyn DatagramCipher>>) -> Self {
 		let server_clone = server.clone();
+		let send_cipher = cipher.clone();
+		let server_loss = server.clone();
+		let server_export = server.clone();
+		let resend_timeout = server.config.resend_timeout();
+		let resend_limit = server.config.resend_limit();
+		let mtu_size = datagram_mtu_size(mtu_size, cipher.as_deref());
+		let congestion_controller = server.config.create_congestion_controller(mtu_size);
+		let mut send_layer = SendReliabilityLayer::with_resend_policy(
+			mtu_size,
+			move | datagram | {
+				server.send_session_packet(datagram, send_cipher.as_deref(), socket_id, &address)
+			},
+			move | identifier_ack | {
+				server_clone.event_listener.lock().on_packet_ack(internal_id, identifier_ack)
+			},
+			move | identifier_ack | {
+				server_loss.event_listener.lock().on_packet_loss(internal_id, identifier_ack)
+			},
+			resend_timeout,
+			resend_limit
+		);
+		send_layer.set_congestion_controller(congestion_controller);
+		send_layer.set_max_split_part_count(server_export.config.max_split_part_count());
 		Self {
-			server: server.clone(),
+			server: server_export,
 			client_id,
 			address: address.clone(),
+			socket_id,
 			internal_id,
+			cipher,
 			is_temporal: Mutex::new(true),
 			state: Mutex::new(SessionState::Connecting),
 			last_ping_measure: Mutex::new(Default::default()),
-			send_layer: Mutex::new(SendReliabilityLayer::new(
-				mtu_size,
-				move | datagram | {
-					server.send_packet(datagram, &address)
-				},
-				move | identifier_ack | {
-					server_clone.event_listener.lock().on_packet_ack(internal_id, identifier_ack)
-				},
-			)),
+			send_layer: Mutex::new(send_layer),
 		}
 	}
 
@@ -237,57 +276,18 @@ impl<'a> SessionExport<'a> {
 	}
 
 	pub fn send_packet(&self, packet: &impl PacketImpl) {
-		self.server.send_packet(packet, &self.address);
+		self.server.send_session_packet(packet, self.cipher.as_deref(), self.socket_id, &self.address);
 	}
 
 	fn handle_encapsulated_packet_route(&self, packet: &mut EncapsulatedPacket) {
-		let id = packet.buffer[0];
-		let mut buffer: &[u8] = &packet.buffer;
+		let id = match packet.buffer.first() {
+			Some(id) => *id,
+			None => return
+		};
 		let state = self.state.lock().deref().clone();
 		if id < MessageIdentifiers::UserPacketEnum as u8{ //internal data packet
-			let id = MessageIdentifiers::try_from(id).unwrap();
-			if state == SessionState::Connecting {
-				match id {
-					ConnectionRequest::ID => {
-						let data_packet = ConnectionRequest::decode_packet(&mut buffer);
-						self.send_layer.lock().queue_connected_packet(&ConnectionRequestAccepted::create(
-							self.address.clone(),
-							vec![],
-							data_packet.send_ping_time,
-							self.server.get_raknet_time()
-						), PacketReliability::Unreliable, 0, true);
-					},
-					NewIncomingConnection::ID => {
-						let data_packet = NewIncomingConnection::decode_packet(&mut buffer);
-						if data_packet.address.port() == self.server.get_port() || self.server.get_port_checking() {
-							*self.state.lock() = SessionState::Connected; //FINALLY!
-							*self.is_temporal.lock() = false;
-							self.server.open_session(self);
-
-							//self.handle.pong(data_packet.send_ping_time, data_packet_send_pont_time); //can't use this due to system-address count issues in MCPE >.<
-							self.send_ping();
-						}
-					},
-					_ => {}
-				};
-			} else {
-				match id {
-					DisconnectionNotification::ID => {
-						self.initiate_disconnect("client disconnect");
-					},
-					ConnectedPing::ID => {
-						let data_packet = ConnectedPing::decode_packet(&mut buffer);
-						self.send_layer.lock().queue_connected_packet(&ConnectedPong {
-							send_ping_time: data_packet.send_ping_time,
-							send_pong_time: self.server.get_raknet_time()
-						}, PacketReliability::Unreliable, 0, false);
-					},
-					ConnectedPong::ID => {
-						let data_packet = ConnectedPong::decode_packet(&mut buffer);
-						self.handle_pong(data_packet.send_ping_time, data_packet.send_pong_time);
-					},
-					_ => {}
-				}
+			if let Err(e) = self.handle_internal_packet(id, state, &packet.buffer) {
+				debug!("Dropped bad packet from {}: {}", self.address, e);
 			}
 		} else if state == SessionState::Connected {
 			self.server.event_listener.lock().on_packet_receive(self.internal_id, &packet.buffer)
@@ -296,13 +296,76 @@ impl<'a> SessionExport<'a> {
 		}
 	}
 
+	fn handle_internal_packet(&self, id: u8, state: SessionState, mut buffer: &[u8]) -> Result<(), DecodeError> {
+		let id = match MessageIdentifiers::try_from(id) {
+			Ok(id) => id,
+			Err(_) => return Ok(())
+		};
+		if state == SessionState::Connecting {
+			match id {
+				ConnectionRequest::ID => {
+					let data_packet = ConnectionRequest::decode_packet(&mut buffer)?;
+					self.send_layer.lock().queue_connected_packet(&ConnectionRequestAccepted::create(
+						self.address.clone(),
+						vec![],
+						data_packet.send_ping_time,
+						self.server.get_raknet_time()
+					), PacketReliability::Unreliable, 0, true);
+				},
+				NewIncomingConnection::ID => {
+					let data_packet = NewIncomingConnection::decode_packet(&mut buffer)?;
+					if self.server.has_port(data_packet.address.port()) || !self.server.get_port_checking() {
+						*self.state.lock() = SessionState::Connected; //FINALLY!
+						*self.is_temporal.lock() = false;
+						self.server.open_session(self);
+
+						//self.handle.pong(data_packet.send_ping_time, data_packet_send_pont_time); //can't use this due to system-address count issues in MCPE >.<
+						self.send_ping();
+					}
+				},
+				_ => {}
+			};
+		} else {
+			match id {
+				DisconnectionNotification::ID => {
+					self.initiate_disconnect("client disconnect");
+				},
+				ConnectedPing::ID => {
+					let data_packet = ConnectedPing::decode_packet(&mut buffer)?;
+					self.send_layer.lock().queue_connected_packet(&ConnectedPong {
+						send_ping_time: data_packet.send_ping_time,
+						send_pong_time: self.server.get_raknet_time()
+					}, PacketReliability::Unreliable, 0, false);
+				},
+				ConnectedPong::ID => {
+					let data_packet = ConnectedPong::decode_packet(&mut buffer)?;
+					self.handle_pong(data_packet.send_ping_time, data_packet.send_pong_time);
+				},
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+
 	//TODO: clock differential stuff
 	fn handle_pong(&self, send_ping_time: RaknetTime, _send_pong_time: RaknetTime) {
 		let mut last_ping_measure = self.last_ping_measure.lock();
-		*last_ping_measure = self.server.get_raknet_time() - send_ping_time;
+		*last_ping_measure = self.server.get_raknet_time().saturating_sub(send_ping_time);
+		self.send_layer.lock().add_rtt_sample(*last_ping_measure);
 		self.server.event_listener.lock().on_ping_measure(self.internal_id, *last_ping_measure);
 	}
 
+	pub fn get_last_ping_measure(&self) -> Duration {
+		*self.last_ping_measure.lock()
+	}
+
+	/**
+	 * Round trip time statistics from acknowledgements and pings
+	 */
+	pub fn get_rtt(&self) -> RttEstimator {
+		*self.send_layer.lock().get_rtt()
+	}
+
 	pub fn is_temporal(&self) -> bool {
 		*self.is_temporal.lock()
 	}
@@ -332,10 +395,14 @@ impl<'a> SessionExport<'a> {
 	 * Disconnects the session with immediate effect, regardless of current session state. Usually used in timeout cases.
 	 */
 	pub fn forcibly_disconnect(&self, reason: &str) {
+		let was_connected = self.is_connected();
 		*self.state.lock() = SessionState::Disconnected {
 			disconnection_time: Instant::now()
 		};
-		self.server.event_listener.lock().on_client_disconnect(self.internal_id, reason);
+		//a graceful disconnect already told the event listener
+		if was_connected {
+			self.server.event_listener.lock().on_client_disconnect(self.internal_id, reason);
+		}
 		debug!("Forcibly disconnecting session due to \"{}\"", reason);
 	}
 
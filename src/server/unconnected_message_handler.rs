@@ -1,7 +1,12 @@
 use std::net::SocketAddr;
-use crate::protocol::{MessageIdentifierHeader, OfflineMessageImpl, UnconnectedPing, OpenConnectionRequest1, OpenConnectionRequest2, UnconnectedPingOpenConnections, UnconnectedPong, IncompatibleProtocolVersion, OpenConnectionReply1, OpenConnectionReply2, DecodePacket, PacketImpl};
+use crate::protocol::{MessageIdentifierHeader, OfflineMessageImpl, UnconnectedPing, OpenConnectionRequest1, OpenConnectionRequest2, UnconnectedPingOpenConnections, UnconnectedPong, IncompatibleProtocolVersion, OpenConnectionReply1, OpenConnectionReply2, NoFreeIncomingConnections, ConnectionBanned, IpRecentlyConnected, DecodePacket, PacketImpl, DecodeError};
+#[cfg(feature = "security")]
+use crate::protocol::RemoteSystemRequiresPubKey;
+#[cfg(feature = "security")]
+use crate::generic::{DatagramCipher, SessionCipher};
+#[cfg(feature = "security")]
+use std::sync::Arc;
 use log::{info, debug};
-use crate::server::SessionInternal;
 use std::cmp::min;
 use std::convert::TryInto;
 
@@ -11,36 +16,39 @@ use bytes::Buf;
 use std::ops::Deref;
 
 pub(super) trait UnconnectedMessageHandler {
-	fn handle_raw(&mut self, address: &SocketAddr, raw: &[u8]) -> bool;
+	fn handle_raw(&mut self, socket_id: usize, address: &SocketAddr, raw: &[u8]) -> bool;
 }
 
-fn get_packet(buffer: &mut &[u8]) -> Option<Box<dyn OfflineMessageImpl>> {
+fn get_packet(buffer: &mut &[u8], use_cookies: bool) -> Result<Option<Box<dyn OfflineMessageImpl>>, DecodeError> {
 	if let Ok(id) = buffer[0].try_into() {
-		Some(match id {
-			UnconnectedPing::ID => Box::new(UnconnectedPing::decode_packet(buffer)),
-			UnconnectedPingOpenConnections::ID => Box::new(UnconnectedPong::decode_packet(buffer)),
-			OpenConnectionRequest1::ID => Box::new(OpenConnectionRequest1::decode_packet(buffer)),
-			OpenConnectionRequest2::ID => Box::new(OpenConnectionRequest2::decode_packet(buffer)),
-			UnconnectedPingOpenConnections::ID => Box::new(UnconnectedPingOpenConnections::decode_packet(buffer)),
-			_ => return None
-		})
+		Ok(Some(match id {
+			UnconnectedPing::ID => Box::new(UnconnectedPing::decode_packet(buffer)?),
+			UnconnectedPingOpenConnections::ID => Box::new(UnconnectedPingOpenConnections::decode_packet(buffer)?),
+			OpenConnectionRequest1::ID => Box::new(OpenConnectionRequest1::decode_packet(buffer)?),
+			OpenConnectionRequest2::ID if use_cookies => Box::new(OpenConnectionRequest2::decode_with_cookie(buffer)?),
+			OpenConnectionRequest2::ID => Box::new(OpenConnectionRequest2::decode_packet(buffer)?),
+			_ => return Ok(None)
+		}))
 	} else {
-		None
+		Ok(None)
 	}
 }
 
 impl UnconnectedMessageHandler for ServerInternal<'_> {
 
-	fn handle_raw(&mut self, address: &SocketAddr, mut raw: &[u8]) -> bool{
+	fn handle_raw(&mut self, socket_id: usize, address: &SocketAddr, mut raw: &[u8]) -> bool{
 		if raw.is_empty() {
 			return false;
 		}
 
-		let offline_message = get_packet(&mut raw);
-		if offline_message.is_none() {
-			return false;
-		}
-		let mut offline_message = offline_message.unwrap();
+		let offline_message = match get_packet(&mut raw, self.cookie_jar.is_some()) {
+			Ok(Some(offline_message)) => offline_message,
+			Ok(None) => return false,
+			Err(e) => {
+				debug!("Dropped bad offline packet from {}: {}", address, e);
+				return false;
+			}
+		};
 
 		if !offline_message.is_valid() {
 			return false;
@@ -57,48 +65,103 @@ impl UnconnectedMessageHandler for ServerInternal<'_> {
 				send_ping_time: offline_message.send_ping_time,
 				server_id: self.id,
 				server_name: self.name.to_owned()
-			}, address)
+			}, socket_id, address)
 		} else if let Some(offline_message) = offline_message.as_any().downcast_ref::<UnconnectedPingOpenConnections>() {
 			self.send_packet(&UnconnectedPong {
 				offline_message: Default::default(),
 				send_ping_time: offline_message.send_ping_time,
 				server_id: self.id,
 				server_name: self.name.to_owned()
-			}, address)
+			}, socket_id, address)
 		} else if let Some(offline_message) = offline_message.as_any().downcast_ref::<OpenConnectionRequest1>() {
 			if !self.protocol_acceptor.accepts(offline_message.protocol) {
 				self.send_packet(&IncompatibleProtocolVersion::create(
 					self.protocol_acceptor.get_primary_version(),
 					self.id
-				), address);
+				), socket_id, address);
 				info!("Refused connection from {} due to incompatible RakNet protocol version (version {})", address, offline_message.protocol);
 			} else {
+				let cookie = self.cookie_jar.as_mut().map(| cookie_jar | cookie_jar.generate(address));
+				#[cfg(feature = "security")]
+				let public_key = self.config.key_pair().map(| key_pair | key_pair.get_public_key());
+				#[cfg(not(feature = "security"))]
+				let public_key = None;
 				//IP header size (20 bytes) + UDP header size (8 bytes)
 				self.send_packet(&OpenConnectionReply1::create(
-					self.id, false, offline_message.mtu_size + 28
-				), address);
+					self.id, cookie, public_key, offline_message.mtu_size + 28
+				), socket_id, address);
 			}
 		} else if let Some(offline_message) = offline_message.as_any().downcast_ref::<OpenConnectionRequest2>() {
-			if offline_message.server_address.port() == self.get_port() || !self.get_port_checking() {
-				if (offline_message.mtu_size as usize) < SessionInternal::MIN_MTU_SIZE {
+			if self.has_port(offline_message.server_address.port()) || !self.get_port_checking() {
+				if let Some(cookie_jar) = &mut self.cookie_jar {
+					if !offline_message.cookie.is_some_and(| cookie | cookie_jar.verify(address, cookie)) {
+						debug!("Not creating session for {} due to invalid cookie", address);
+						return false;
+					}
+				}
+				if (offline_message.mtu_size as usize) < self.config.min_mtu_size() {
 					debug!("Not creating session for {} due to bad MTU size {}", address, offline_message.mtu_size);
 					return false;
 				}
-				let mtu_size = min(offline_message.mtu_size, self.max_mtu_size as u16);
+				if self.get_session_count() >= self.config.max_connections() {
+					self.send_packet(&NoFreeIncomingConnections::create(self.id), socket_id, address);
+					debug!("Not creating session for {} due to the limit of {} connections", address, self.config.max_connections());
+					return true;
+				}
+				if self.get_session_count_by_ip(&address.ip()) >= self.config.max_connections_per_ip() {
+					self.send_packet(&ConnectionBanned::create(self.id), socket_id, address);
+					debug!("Not creating session for {} due to the limit of {} connections per IP", address, self.config.max_connections_per_ip());
+					return true;
+				}
+				if self.is_recently_connected(&address.ip()) {
+					self.send_packet(&IpRecentlyConnected::create(self.id), socket_id, address);
+					debug!("Not creating session for {} due to its IP connecting recently", address);
+					return true;
+				}
+				#[cfg(feature = "security")]
+				let (answer, cipher) = match self.config.key_pair() {
+					Some(key_pair) => match offline_message.challenge.as_ref().map(| challenge | SessionCipher::server(key_pair, challenge)) {
+						Some(Some((cipher, answer))) => (Some(answer), Some(Arc::new(cipher) as Arc<dyn DatagramCipher>)),
+						Some(None) => {
+							debug!("Not creating session for {} due to bad handshake challenge", address);
+							return false;
+						},
+						None => {
+							self.send_packet(&RemoteSystemRequiresPubKey::create(self.id), socket_id, address);
+							debug!("Not creating session for {} due to missing handshake challenge", address);
+							return true;
+						}
+					},
+					None => (None, None)
+				};
+				#[cfg(not(feature = "security"))]
+				let (answer, cipher) = (None, None);
+				let mtu_size = min(offline_message.mtu_size, self.config.max_mtu_size() as u16);
 				self.send_packet(&OpenConnectionReply2::create(
 					self.id,
 					address.to_owned(),
 					mtu_size,
-					false
-				), address);
-				self.create_session(address.clone(), offline_message.client_id, mtu_size as usize);
+					answer
+				), socket_id, address);
+				self.create_session(socket_id, address.clone(), offline_message.client_id, mtu_size as usize, cipher);
 			}
 		} else {
-			panic!("invalid packet");
+			return false;
 		}
-		//TODO error handle (buffer underflow)
-
 
 		true
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::protocol::{NoFreeIncomingConnections, ConnectionBanned, IpRecentlyConnected};
+	use crate::protocol::tests::round_trip;
+
+	#[test]
+	fn connection_refused() {
+		assert_eq!(round_trip(&NoFreeIncomingConnections::create(42)).server_id, 42);
+		assert_eq!(round_trip(&ConnectionBanned::create(42)).server_id, 42);
+		assert_eq!(round_trip(&IpRecentlyConnected::create(42)).server_id, 42);
+	}
+}